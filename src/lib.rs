@@ -5,25 +5,34 @@
 // =============================
 
 mod ciyi_game {
-    use kovi::chrono::{DateTime, Duration, Utc};
+    use kovi::chrono::{self, DateTime, Datelike, NaiveDate, Utc};
     use kovi::log;
-    use kovi::utils::{load_json_data, save_json_data};
+    use once_cell::sync::Lazy;
     use serde::{Deserialize, Serialize};
-    use std::cmp::Ordering;
+    use std::cmp::{Ordering, Reverse};
 
     use std::collections::{HashMap, HashSet};
     use std::error::Error;
     use std::path::PathBuf;
+    use std::sync::Arc;
+    use tokio::sync::oneshot;
 
-    use crate::p_config;
+    use crate::p_config::{
+        self, DayCycle, GiveUpConfig, GiveUpMode, HintConfig, LeaderboardSort, ScheduleConfig,
+        SeasonPeriod, StorageBackend, UnsolvedPolicy,
+    };
     use crate::p_const::ALL_WORDS;
-    use crate::p_const::QUESTION_WORDS;
+    use crate::p_const::TARGET_WORDS;
+    use crate::p_rank::{self, RankListResult};
+    use crate::p_storage::{self, StorageWriter};
 
     #[derive(Debug, Clone, Default, Serialize, Deserialize)]
     pub struct UserScore {
         pub user_id: String,
         pub username: String,
         pub score: u32,
+        pub first_of_day_wins: u32,
+        pub points: u32,
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +42,251 @@ mod ciyi_game {
         pub channel_id: String,
         #[serde(with = "chrono::serde::ts_seconds")]
         pub timestamp: DateTime<Utc>,
+        /// 当天该频道的第一局，之后的加赛局为 false。
+        pub first_of_day: bool,
+        /// 本局使用「词意提示」的次数。
+        pub hints_used: u32,
+        /// 按 [score] 配置计算的得分。
+        pub points: u32,
+        /// 本局的总猜测次数，升级前的记录为 `None`。
+        pub guess_count: Option<usize>,
+    }
+
+    /// 赛季：按 [season] 配置的周期划分的自然月、季度或年，以游戏日计。
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct Season {
+        pub period: SeasonPeriod,
+        pub year: i32,
+        /// 年内的序号，从 1 开始
+        pub index: u32,
+    }
+
+    impl Season {
+        pub fn containing(period: SeasonPeriod, day: NaiveDate) -> Self {
+            let mut season = Self {
+                period,
+                year: day.year(),
+                index: 1,
+            };
+            season.index = (day.month() - 1) / season.months() + 1;
+            season
+        }
+
+        /// 解析 [`Season::name`] 的格式。年份须为四位数字，以免和排行榜的页码混淆。
+        pub fn parse(period: SeasonPeriod, name: &str) -> Option<Self> {
+            let (year, index) = match period {
+                SeasonPeriod::Month => {
+                    let (year, month) = name.split_once('-')?;
+                    (year, month.parse().ok()?)
+                }
+                SeasonPeriod::Quarter => {
+                    let (year, quarter) = name.split_once(['Q', 'q'])?;
+                    (year, quarter.parse().ok()?)
+                }
+                SeasonPeriod::Year => (name, 1),
+            };
+            if year.len() != 4 || !year.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let year: i32 = year.parse().ok()?;
+            let season = Self {
+                period,
+                year,
+                index,
+            };
+            let valid = (1..=12 / season.months()).contains(&index)
+                && NaiveDate::from_ymd_opt(year, 1, 1).is_some();
+            valid.then_some(season)
+        }
+
+        pub fn name(&self) -> String {
+            match self.period {
+                SeasonPeriod::Month => format!("{}-{:02}", self.year, self.index),
+                SeasonPeriod::Quarter => format!("{}Q{}", self.year, self.index),
+                SeasonPeriod::Year => self.year.to_string(),
+            }
+        }
+
+        pub fn first_day(&self) -> NaiveDate {
+            let month = (self.index - 1) * self.months() + 1;
+            NaiveDate::from_ymd_opt(self.year, month, 1).unwrap_or_default()
+        }
+
+        pub fn last_day(&self) -> NaiveDate {
+            self.next().first_day().pred_opt().unwrap_or_default()
+        }
+
+        pub fn next(&self) -> Self {
+            if self.index * self.months() >= 12 {
+                Self {
+                    year: self.year + 1,
+                    index: 1,
+                    ..*self
+                }
+            } else {
+                Self {
+                    index: self.index + 1,
+                    ..*self
+                }
+            }
+        }
+
+        fn months(&self) -> u32 {
+            match self.period {
+                SeasonPeriod::Month => 1,
+                SeasonPeriod::Quarter => 3,
+                SeasonPeriod::Year => 12,
+            }
+        }
+    }
+
+    /// 还有频道的游戏日停留在其中的最早赛季。各频道按自己的时区换日，
+    /// 早于它的赛季才不会再有新的胜场，可以存档。
+    fn open_season(schedule: &ScheduleConfig, period: SeasonPeriod, now: DateTime<Utc>) -> Season {
+        let season_of = |cycle: DayCycle| Season::containing(period, cycle.game_day(now));
+        schedule
+            .channels
+            .keys()
+            .map(|channel_id| season_of(schedule.cycle(channel_id)))
+            .fold(season_of(schedule.default_cycle()), |earliest, season| {
+                if season.first_day() < earliest.first_day() {
+                    season
+                } else {
+                    earliest
+                }
+            })
+    }
+
+    /// 排行榜的统计范围，按游戏日划分。
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TimeWindow {
+        All,
+        Today,
+        /// 本周一至今
+        Week,
+        /// 本月 1 日至今
+        Month,
+        CurrentSeason,
+        Season(Season),
+        /// 起止日期，含两端
+        Range(NaiveDate, NaiveDate),
+    }
+
+    impl TimeWindow {
+        fn bounds(self, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+            match self {
+                Self::All => None,
+                Self::Today => Some((today, today)),
+                Self::Week => {
+                    let offset = today.weekday().num_days_from_monday();
+                    Some((today - chrono::Days::new(offset.into()), today))
+                }
+                Self::Month => Some((today.with_day(1).unwrap_or(today), today)),
+                Self::CurrentSeason => {
+                    let season = Season::containing(p_config::config().season.period, today);
+                    Some((season.first_day(), season.last_day()))
+                }
+                Self::Season(season) => Some((season.first_day(), season.last_day())),
+                Self::Range(start, end) => Some((start, end)),
+            }
+        }
+
+        fn title(self, today: NaiveDate) -> Option<String> {
+            let (start, end) = self.bounds(today)?;
+            let label = match self {
+                Self::All => return None,
+                Self::Today => return Some(format!("今日（{today}）")),
+                Self::Range(..) => return Some(format!("{start} ~ {end}")),
+                Self::Week => "本周".to_string(),
+                Self::Month => "本月".to_string(),
+                Self::CurrentSeason => {
+                    let season = Season::containing(p_config::config().season.period, today);
+                    format!("本赛季 {}", season.name())
+                }
+                Self::Season(season) => format!("赛季 {}", season.name()),
+            };
+            Some(format!("{label}（{start} ~ {end}）"))
+        }
+    }
+
+    /// 赛季结束时冻结的最终排名。
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct SeasonArchive {
+        pub season: Season,
+        /// 频道榜的群号，全服榜为 `None`
+        pub channel_id: Option<String>,
+        pub sort: LeaderboardSort,
+        pub standings: Vec<UserScore>,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        pub archived_at: DateTime<Utc>,
+    }
+
+    /// 排行榜指令的参数。
+    #[derive(Debug, Clone, Copy)]
+    pub struct LeaderboardQuery {
+        pub sort: LeaderboardSort,
+        pub window: TimeWindow,
+        /// 页码，从 1 开始，每页 rank_display 条
+        pub page: usize,
+    }
+
+    /// 用户所有猜测的累计，用于「词意我的」。
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct GuessStats {
+        pub guesses: u64,
+        /// 在排名列表中的猜测次数与名次之和，用于计算平均名次。
+        pub ranked_guesses: u64,
+        pub rank_sum: u64,
+    }
+
+    /// 猜对的一局里，猜到排名前列的其他成员。
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct AssistRecord {
+        pub user_id: String,
+        pub username: String,
+        pub channel_id: String,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        pub timestamp: DateTime<Utc>,
+        /// 该成员本局猜到的最好名次。
+        pub best_rank: usize,
+        pub points: u32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum RoundOutcome {
+        Solved,
+        /// 放弃后公布了答案，没有人猜中。
+        GaveUp,
+        /// 到换题时间仍无人猜中，自动公布了答案。
+        Expired,
+    }
+
+    /// 已结束的一轮。
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct RoundRecord {
+        pub channel_id: String,
+        pub round: u64,
+        pub puzzle_number: Option<u32>,
+        pub target_word: String,
+        pub outcome: RoundOutcome,
+        pub guess_count: usize,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        pub started_at: DateTime<Utc>,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        pub ended_at: DateTime<Utc>,
+    }
+
+    /// 本局的一次猜测。
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct GuessRecord {
+        pub user_id: String,
+        pub username: String,
+        pub word: String,
+        /// 在排名列表中的名次，不在列表中为 `None`。
+        pub rank: Option<usize>,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        pub timestamp: DateTime<Utc>,
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -60,22 +314,493 @@ mod ciyi_game {
         #[serde(with = "chrono::serde::ts_seconds")]
         pub last_start_time: DateTime<Utc>,
         pub global_history: HashSet<String>,
-        pub current_guesses: HashSet<String>,
+        /// 本局按时间顺序的猜测。
+        pub guesses: Vec<GuessRecord>,
         pub words_rank_list: Vec<String>,
         pub hints: Vec<Hint>,
         pub is_finished: bool,
-        #[serde(default)]
         pub direct_guess_enabled: bool,
+        pub pending_round: Option<PendingRound>,
+        /// 每开始新的一轮加一，用于识别过期的获取结果。
+        pub round: u64,
+        /// 全局每日模式下本轮的题号。
+        pub puzzle_number: Option<u32>,
+        /// 本局已使用「词意提示」的次数。
+        pub hints_used: u32,
+        /// 投票放弃本轮的用户，换轮或重启后清空。
+        #[serde(skip)]
+        pub give_up_votes: HashSet<String>,
+    }
+
+    /// 预先选好并获取了排名列表的下一轮，换日时直接使用。
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PendingRound {
+        pub target_word: String,
+        pub words_rank_list: Vec<String>,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        pub fetched_at: DateTime<Utc>,
+        pub puzzle_number: Option<u32>,
+    }
+
+    impl PendingRound {
+        /// 预取的一轮是否已能满足 `puzzle` 这一题（全局模式下可以是更靠后的题）。
+        fn covers(&self, puzzle: Option<u32>) -> bool {
+            match (self.puzzle_number, puzzle) {
+                (Some(pending), Some(puzzle)) => pending >= puzzle,
+                (pending, puzzle) => pending == puzzle,
+            }
+        }
     }
 
     impl CiYiGameState {
-        pub fn is_new_day_in_china_timezone(&self) -> bool {
-            const CHINA_TIMEZONE_OFFSET_HOURS: i64 = 8;
-            let now_in_china_tz = Utc::now() + Duration::hours(CHINA_TIMEZONE_OFFSET_HOURS);
-            let last_start_in_china_tz =
-                self.last_start_time + Duration::hours(CHINA_TIMEZONE_OFFSET_HOURS);
-            now_in_china_tz.date_naive() != last_start_in_china_tz.date_naive()
+        fn start_round(
+            &mut self,
+            target_word: String,
+            words_rank_list: Vec<String>,
+            puzzle_number: Option<u32>,
+        ) {
+            self.hints.clear();
+            self.guesses.clear();
+            self.global_history.insert(target_word.clone());
+            self.target_word = target_word;
+            self.words_rank_list = words_rank_list;
+            self.last_start_time = Utc::now();
+            self.is_finished = false;
+            self.round += 1;
+            self.puzzle_number = puzzle_number;
+            self.give_up_votes.clear();
+            self.hints_used = 0;
+        }
+
+        fn has_guessed(&self, word: &str) -> bool {
+            self.guesses.iter().any(|g| g.word == word)
+        }
+
+        /// 升级前的猜测没有用户，不计入参与人数。
+        fn participant_count(&self) -> usize {
+            self.guesses
+                .iter()
+                .map(|g| g.user_id.as_str())
+                .filter(|user_id| !user_id.is_empty())
+                .collect::<HashSet<_>>()
+                .len()
+        }
+
+        /// 猜对者的得分：基础分减去本局猜测与提示的扣分，
+        /// 加上猜对者自己猜测的占比与其猜过的最好名次的加分。
+        fn win_points(&self, user_id: &str) -> u32 {
+            let config = &p_config::config().score;
+            let total = self.guesses.len().max(1) as f64;
+            let own: Vec<&GuessRecord> = self
+                .guesses
+                .iter()
+                .filter(|g| g.user_id == user_id)
+                .collect();
+            let best_rank = own
+                .iter()
+                .filter(|g| g.word != self.target_word)
+                .filter_map(|g| g.rank)
+                .min();
+            let points = config.base - config.per_guess_penalty * total
+                + config.own_guess_bonus * own.len() as f64 / total
+                - config.per_hint_penalty * self.hints_used as f64
+                + best_rank.map_or(0.0, |rank| config.best_rank_bonus / rank as f64);
+            points.max(config.min_points).round() as u32
+        }
+
+        /// 除猜对者外，猜到前 `top_k` 名的成员及其最好名次，按名次排列。
+        fn assists(&self, winner_id: &str, top_k: usize) -> Vec<(&GuessRecord, usize)> {
+            let mut best: HashMap<&str, (&GuessRecord, usize)> = HashMap::new();
+            for guess in &self.guesses {
+                if guess.user_id.is_empty() || guess.user_id == winner_id {
+                    continue;
+                }
+                let Some(rank) = guess.rank.filter(|&rank| rank <= top_k) else {
+                    continue;
+                };
+                if guess.word == self.target_word {
+                    continue;
+                }
+                let entry = best.entry(&guess.user_id).or_insert((guess, rank));
+                if rank < entry.1 {
+                    *entry = (guess, rank);
+                }
+            }
+            let mut assists: Vec<(&GuessRecord, usize)> = best.into_values().collect();
+            assists.sort_by_key(|&(_, rank)| rank);
+            assists
+        }
+
+        /// 除答案本身外排名最靠前的猜测。
+        fn closest_guess(&self) -> Option<&GuessRecord> {
+            self.guesses
+                .iter()
+                .filter(|g| g.rank.is_some() && g.word != self.target_word)
+                .min_by_key(|g| g.rank)
+        }
+
+        /// 按排名排列的前 `history_display` 条提示，每条一行。
+        fn hint_lines(&self) -> String {
+            self.hints
+                .iter()
+                .take(p_config::config().plugin.history_display)
+                .enumerate()
+                .map(|(i, hint)| format!("{}. {}\n", i + 1, hint.text))
+                .collect()
+        }
+
+        /// 依次变强的提示：排名列表中指定名次的词语，最后是目标词语的第一个字。
+        fn clues(&self, ranks: &[usize]) -> Vec<String> {
+            let mut clues: Vec<String> = ranks
+                .iter()
+                .filter_map(|&rank| {
+                    let word = self.words_rank_list.get(rank.checked_sub(1)?)?;
+                    (*word != self.target_word).then(|| format!("排名第 {rank} 的词语是「{word}」"))
+                })
+                .collect();
+            if let Some(first) = self.target_word.chars().next() {
+                clues.push(format!("目标词语的第一个字是「{first}」"));
+            }
+            clues
+        }
+
+        fn finish(&mut self, outcome: RoundOutcome) -> RoundRecord {
+            self.is_finished = true;
+            RoundRecord {
+                channel_id: self.channel_id.clone(),
+                round: self.round,
+                puzzle_number: self.puzzle_number,
+                target_word: self.target_word.clone(),
+                outcome,
+                guess_count: self.guesses.len(),
+                started_at: self.last_start_time,
+                ended_at: Utc::now(),
+            }
+        }
+
+        /// 全局每日模式下消息开头的题号。
+        fn puzzle_title(&self) -> String {
+            self.puzzle_number
+                .map_or_else(String::new, |n| format!("词意 #{n}\n"))
         }
+
+        /// 新频道里还没有排名列表、也没有人猜过的初始轮次。
+        fn is_placeholder(&self) -> bool {
+            self.round == 0
+                && !self.is_finished
+                && self.words_rank_list.is_empty()
+                && self.guesses.is_empty()
+        }
+
+        /// 已结束的一轮进入新的一天时，直接使用预取的下一轮。
+        fn roll_over(&mut self) {
+            self.roll_over_to(current_puzzle_number());
+        }
+
+        /// 换日到第 `puzzle` 题：预取的正是这一题就开始新一轮，预取了之后的题则保留，
+        /// 其余的预取已经过时，直接丢弃。
+        fn roll_over_to(&mut self, puzzle: Option<u32>) {
+            if !self.is_finished || !self.is_new_day() {
+                return;
+            }
+            let Some(pending) = self.pending_round.take() else {
+                return;
+            };
+            if pending.puzzle_number == puzzle {
+                self.start_round(
+                    pending.target_word,
+                    pending.words_rank_list,
+                    pending.puzzle_number,
+                );
+            } else if pending.covers(puzzle) {
+                // 预取的是之后的题，留到那天再用
+                self.pending_round = Some(pending);
+            }
+        }
+
+        fn pick_next_target(&self, rejected: &HashSet<String>) -> Option<String> {
+            pick_target(|w| self.global_history.contains(w) || rejected.contains(w))
+        }
+
+        /// 按频道配置的时区与换题时刻，判断本轮开始后是否已进入新的一天。
+        pub fn is_new_day(&self) -> bool {
+            p_config::config()
+                .schedule
+                .cycle(&self.channel_id)
+                .is_new_day(self.last_start_time, Utc::now())
+        }
+    }
+
+    fn pick_target(excluded: impl Fn(&str) -> bool) -> Option<String> {
+        let candidates: Vec<&str> = TARGET_WORDS
+            .iter()
+            .map(|w| w.as_str())
+            .filter(|w| !excluded(w))
+            .collect();
+
+        if candidates.is_empty() {
+            return None;
+        }
+
+        Some(candidates[fastrand::usize(..candidates.len())].to_string())
+    }
+
+    /// 排行榜统计的记录：每条计一次，并累加其积分。
+    trait ScoreRecord {
+        /// 还没有记录时排行榜显示的内容。
+        const EMPTY: &'static str;
+
+        fn user_id(&self) -> &str;
+        fn username(&self) -> &str;
+        fn channel_id(&self) -> &str;
+        fn timestamp(&self) -> DateTime<Utc>;
+        fn points(&self) -> u32;
+        fn first_of_day(&self) -> bool;
+        fn standing_line(rank: usize, user_score: &UserScore, sort: LeaderboardSort) -> String;
+    }
+
+    impl ScoreRecord for WinRecord {
+        const EMPTY: &'static str = "当前还没有人猜对过哦！";
+
+        fn user_id(&self) -> &str {
+            &self.user_id
+        }
+
+        fn username(&self) -> &str {
+            &self.username
+        }
+
+        fn channel_id(&self) -> &str {
+            &self.channel_id
+        }
+
+        fn timestamp(&self) -> DateTime<Utc> {
+            self.timestamp
+        }
+
+        fn points(&self) -> u32 {
+            self.points
+        }
+
+        fn first_of_day(&self) -> bool {
+            self.first_of_day
+        }
+
+        fn standing_line(rank: usize, user_score: &UserScore, sort: LeaderboardSort) -> String {
+            standing_line(rank, user_score, sort)
+        }
+    }
+
+    impl ScoreRecord for AssistRecord {
+        const EMPTY: &'static str = "当前还没有人助攻过哦！";
+
+        fn user_id(&self) -> &str {
+            &self.user_id
+        }
+
+        fn username(&self) -> &str {
+            &self.username
+        }
+
+        fn channel_id(&self) -> &str {
+            &self.channel_id
+        }
+
+        fn timestamp(&self) -> DateTime<Utc> {
+            self.timestamp
+        }
+
+        fn points(&self) -> u32 {
+            self.points
+        }
+
+        /// 助攻不区分每日首局。
+        fn first_of_day(&self) -> bool {
+            false
+        }
+
+        fn standing_line(rank: usize, user_score: &UserScore, _sort: LeaderboardSort) -> String {
+            format!(
+                "{rank}. {} {}（{} 分）",
+                user_score.username, user_score.score, user_score.points
+            )
+        }
+    }
+
+    fn rank_scores<'a, R, I>(records: I, sort: LeaderboardSort) -> Vec<UserScore>
+    where
+        R: ScoreRecord + 'a,
+        I: Iterator<Item = &'a R>,
+    {
+        // 每人达到当前成绩的时间，同分时先达到的排在前面
+        let mut scores: HashMap<String, (UserScore, DateTime<Utc>)> = HashMap::new();
+        for record in records {
+            let (user_score, achieved_at) = scores
+                .entry(record.user_id().to_string())
+                .or_insert_with(|| {
+                    let user_score = UserScore {
+                        user_id: record.user_id().to_string(),
+                        username: record.username().to_string(),
+                        score: 0,
+                        first_of_day_wins: 0,
+                        points: 0,
+                    };
+                    (user_score, record.timestamp())
+                });
+            *achieved_at = (*achieved_at).max(record.timestamp());
+            user_score.username = record.username().to_string();
+            user_score.score += 1;
+            user_score.points += record.points();
+            if record.first_of_day() {
+                user_score.first_of_day_wins += 1;
+            }
+        }
+
+        let mut sorted_scores: Vec<(UserScore, DateTime<Utc>)> = scores.into_values().collect();
+        let key = |s: &UserScore| match sort {
+            LeaderboardSort::Wins => s.score,
+            LeaderboardSort::Points => s.points,
+        };
+        sorted_scores.sort_by(|(a, a_at), (b, b_at)| {
+            key(b)
+                .cmp(&key(a))
+                .then(a_at.cmp(b_at))
+                .then_with(|| a.user_id.cmp(&b.user_id))
+        });
+
+        sorted_scores
+            .into_iter()
+            .map(|(user_score, _)| user_score)
+            .collect()
+    }
+
+    fn format_standings(standings: &[UserScore], sort: LeaderboardSort) -> String {
+        standings
+            .iter()
+            .take(p_config::config().plugin.rank_display)
+            .enumerate()
+            .map(|(index, user_score)| standing_line(index + 1, user_score, sort))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn standing_line(rank: usize, user_score: &UserScore, sort: LeaderboardSort) -> String {
+        let mut line = match sort {
+            LeaderboardSort::Wins => {
+                format!("{rank}. {} {}", user_score.username, user_score.score)
+            }
+            LeaderboardSort::Points => format!(
+                "{rank}. {} {} 分（{} 胜）",
+                user_score.username, user_score.points, user_score.score
+            ),
+        };
+        // 有加赛局的胜场时，单独列出每日首局的胜场
+        if user_score.first_of_day_wins < user_score.score {
+            line.push_str(&format!("（每日首局 {}）", user_score.first_of_day_wins));
+        }
+        line
+    }
+
+    /// 按日期排列、去重后的猜对日期中，截至今天的连胜天数与最长连胜天数。
+    /// 今天还没猜对时，到昨天为止的连胜仍算当前连胜。
+    fn win_streaks(days: &[NaiveDate], today: NaiveDate) -> (usize, usize) {
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for &day in days {
+            run = match previous {
+                Some(prev) if prev.succ_opt() == Some(day) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            previous = Some(day);
+        }
+        let current = match previous {
+            Some(last) if last == today || last.succ_opt() == Some(today) => run,
+            _ => 0,
+        };
+        (current, longest)
+    }
+
+    /// 回放最多列出的猜测次数。
+    const MAX_REPLAY_LINES: usize = 50;
+
+    fn format_duration(duration: chrono::Duration) -> String {
+        let minutes = duration.num_minutes();
+        match (minutes / 60, minutes % 60) {
+            (0, 0) => "不到 1 分钟".to_string(),
+            (0, m) => format!("{m} 分钟"),
+            (h, m) => format!("{h} 小时 {m} 分钟"),
+        }
+    }
+
+    /// 频道在 `instant` 所属的游戏日里开始并已结束的轮数。
+    fn rounds_on_day(records: &[RoundRecord], channel_id: &str, instant: DateTime<Utc>) -> usize {
+        let cycle = p_config::config().schedule.cycle(channel_id);
+        let day = cycle.game_day(instant);
+        records
+            .iter()
+            .filter(|r| r.channel_id == channel_id && cycle.game_day(r.started_at) == day)
+            .count()
+    }
+
+    /// 今天的局数是否还没有达到 `rounds_per_day`。
+    fn may_start_extra_round(records: &[RoundRecord], channel_id: &str) -> bool {
+        let limit = p_config::config().schedule.rounds_per_day;
+        within_round_limit(limit, records, channel_id, Utc::now())
+    }
+
+    /// `instant` 所属游戏日的局数是否少于 `limit`，0 表示不限。
+    fn within_round_limit(
+        limit: usize,
+        records: &[RoundRecord],
+        channel_id: &str,
+        instant: DateTime<Utc>,
+    ) -> bool {
+        limit == 0 || rounds_on_day(records, channel_id, instant) < limit
+    }
+
+    fn finished_message(records: &[RoundRecord], channel_id: &str) -> String {
+        let limit = p_config::config().schedule.rounds_per_day;
+        if limit == 1 {
+            "每天只能玩一次哦！".to_string()
+        } else if may_start_extra_round(records, channel_id) {
+            "本局已结束，发送「词意新局」开始新的一局".to_string()
+        } else {
+            format!("今天的 {limit} 局已经玩完了，明天再来吧！")
+        }
+    }
+
+    /// 全局每日模式的出题顺序：以配置的种子打乱题库。
+    static DAILY_ORDER: Lazy<Vec<String>> = Lazy::new(|| {
+        let mut words = TARGET_WORDS.clone();
+        fastrand::Rng::with_seed(p_config::config().daily.seed).shuffle(&mut words);
+        words
+    });
+
+    /// 全局每日模式下今天的题号，频道独立出题时为 `None`。
+    pub fn current_puzzle_number() -> Option<u32> {
+        let config = p_config::config();
+        if !config.daily.global {
+            return None;
+        }
+        let today = config.schedule.default_cycle().game_day(Utc::now());
+        let days = (today - config.daily.epoch).num_days() + 1;
+        Some(days.max(1) as u32)
+    }
+
+    /// 第 `puzzle` 题的目标词语。题号固定对应 `DAILY_ORDER` 中的位置，
+    /// 该词被拒绝时才向后循环查找，拒绝一个词不会改变其他题号的词语。
+    fn daily_word(puzzle: u32, rejected: &HashSet<String>) -> Option<String> {
+        let len = DAILY_ORDER.len();
+        if len == 0 {
+            return None;
+        }
+        let start = (puzzle as usize - 1) % len;
+        (0..len)
+            .map(|offset| &DAILY_ORDER[(start + offset) % len])
+            .find(|w| !rejected.contains(w.as_str()))
+            .cloned()
     }
 
     #[derive(Debug)]
@@ -83,85 +808,191 @@ mod ciyi_game {
         NewGame,
         NewDay,
         MissingRankList,
+        Prefetch,
+        /// 同一天里本局结束后再开一局。
+        NewRound,
     }
 
     #[derive(Debug)]
     pub struct FetchRequest {
         pub word_to_fetch: String,
         pub reason: FetchReason,
+        /// 发起请求时频道所处的轮次，新游戏为 0。
+        pub round: u64,
+        pub puzzle_number: Option<u32>,
     }
 
     pub struct FetchedData {
         pub request: FetchRequest,
-        pub result: Result<Vec<String>, Box<dyn Error>>,
+        pub result: RankListResult,
     }
 
-    #[derive(Debug, Default, Serialize, Deserialize)]
+    #[derive(Default, Serialize, Deserialize)]
     pub struct CiYiGameManager {
-        states: HashMap<String, CiYiGameState>,
-        win_records: Vec<WinRecord>,
+        pub(crate) states: HashMap<String, CiYiGameState>,
+        pub(crate) win_records: Vec<WinRecord>,
+        pub(crate) rejected_words: HashSet<String>,
+        pub(crate) round_records: Vec<RoundRecord>,
+        pub(crate) assist_records: Vec<AssistRecord>,
+        pub(crate) guess_stats: HashMap<String, GuessStats>,
+        pub(crate) season_archives: Vec<SeasonArchive>,
+        /// 上次检查存档时所在的赛季
+        #[serde(skip)]
+        checked_season: Option<Season>,
+        #[serde(skip)]
+        fetch_locks: HashMap<String, Arc<tokio::sync::Mutex<()>>>,
+        #[serde(skip)]
+        writer: Option<StorageWriter>,
         #[serde(skip)]
-        data_file_path: PathBuf,
+        dirty: bool,
     }
 
     impl CiYiGameManager {
-        pub fn new(data_dir: PathBuf) -> Result<Self, Box<dyn Error>> {
+        pub fn new(data_dir: PathBuf, backend: StorageBackend) -> Result<Self, Box<dyn Error>> {
             if !data_dir.exists() {
                 std::fs::create_dir_all(&data_dir)?;
             }
-            let data_file_path = data_dir.join("ciyi_game_data.json");
-            let mut manager: CiYiGameManager =
-                load_json_data(CiYiGameManager::default(), data_file_path.clone())?;
-            manager.data_file_path = data_file_path;
+            let storage = p_storage::open(&data_dir, backend, &p_config::config().storage)?;
+            let mut manager = storage.load()?;
+            manager.writer = Some(StorageWriter::spawn(storage)?);
             Ok(manager)
         }
 
-        pub fn save(&self) {
-            if let Err(e) = save_json_data(self, &self.data_file_path) {
-                log::error!("Failed to save ciyi game data: {e}");
+        /// 把当前状态的快照交给存储线程写入，不在游戏任务中等待 I/O。
+        pub fn save(&mut self) {
+            let Some(writer) = &self.writer else {
+                return;
+            };
+            if writer.save(self.snapshot()) {
+                self.dirty = false;
+            }
+        }
+
+        /// 保存并返回写入完成的通知，用于插件卸载时在游戏任务外等待。
+        pub fn flush(&mut self) -> oneshot::Receiver<()> {
+            self.save();
+            match &self.writer {
+                Some(writer) => writer.flush(),
+                None => oneshot::channel().1,
+            }
+        }
+
+        fn snapshot(&self) -> CiYiGameManager {
+            CiYiGameManager {
+                states: self.states.clone(),
+                win_records: self.win_records.clone(),
+                rejected_words: self.rejected_words.clone(),
+                round_records: self.round_records.clone(),
+                assist_records: self.assist_records.clone(),
+                guess_stats: self.guess_stats.clone(),
+                season_archives: self.season_archives.clone(),
+                ..Self::default()
+            }
+        }
+
+        /// 状态有变化时调用，`save_interval_secs` 为 0 时立即保存，否则等待定时保存。
+        pub fn mark_dirty(&mut self) {
+            self.dirty = true;
+            if p_config::config().storage.save_interval_secs == 0 {
+                self.save();
+            }
+        }
+
+        /// 上次写入失败时也重新保存。
+        pub fn save_if_dirty(&mut self) {
+            if self.dirty || self.writer.as_ref().is_some_and(StorageWriter::take_failed) {
+                self.save();
+            }
+        }
+
+        /// 全局每日模式按题号出题，否则从频道未用过的词语中随机选择。
+        fn choose_target(
+            &self,
+            state: Option<&CiYiGameState>,
+            puzzle: Option<u32>,
+        ) -> Option<String> {
+            match (puzzle, state) {
+                (Some(puzzle), _) => daily_word(puzzle, &self.rejected_words),
+                (None, Some(state)) => state.pick_next_target(&self.rejected_words),
+                (None, None) => pick_target(|w| self.rejected_words.contains(w)),
             }
         }
 
         pub fn prepare_guess(&self, channel_id: &str) -> Option<FetchRequest> {
+            let puzzle = current_puzzle_number();
             let state = match self.states.get(channel_id) {
                 Some(s) => s,
                 None => {
-                    let target = &QUESTION_WORDS[fastrand::usize(..QUESTION_WORDS.len())];
-                    return Some(FetchRequest {
-                        word_to_fetch: target.to_string(),
+                    return self.choose_target(None, puzzle).map(|target| FetchRequest {
+                        word_to_fetch: target,
                         reason: FetchReason::NewGame,
+                        round: 0,
+                        puzzle_number: puzzle,
                     });
                 }
             };
 
-            if state.is_finished && state.is_new_day_in_china_timezone() {
-                let candidates: Vec<&str> = QUESTION_WORDS
-                    .iter()
-                    .filter(|w| !state.global_history.contains(w.as_str()))
-                    .map(|w| w.as_str())
-                    .collect();
-
-                if candidates.is_empty() {
+            if state.is_finished && state.is_new_day() {
+                // 全局模式下各频道换日时刻可能不同，今天的题已经玩过时不换
+                if puzzle.is_some() && state.puzzle_number == puzzle {
                     return None;
                 }
-
-                let new_target = candidates[fastrand::usize(..candidates.len())].to_string();
-                return Some(FetchRequest {
-                    word_to_fetch: new_target,
-                    reason: FetchReason::NewDay,
-                });
+                // 已有预取的下一轮时由 commit_guess 直接换日
+                if state
+                    .pending_round
+                    .as_ref()
+                    .is_some_and(|p| p.puzzle_number == puzzle)
+                {
+                    return None;
+                }
+                return self
+                    .choose_target(Some(state), puzzle)
+                    .map(|new_target| FetchRequest {
+                        word_to_fetch: new_target,
+                        reason: FetchReason::NewDay,
+                        round: state.round,
+                        puzzle_number: puzzle,
+                    });
             }
 
             if !state.is_finished && state.words_rank_list.is_empty() {
+                // 还没有排名列表的目标词语被拒绝时，换一个词语
+                let word_to_fetch = if self.rejected_words.contains(&state.target_word) {
+                    self.choose_target(Some(state), state.puzzle_number)?
+                } else {
+                    state.target_word.clone()
+                };
                 return Some(FetchRequest {
-                    word_to_fetch: state.target_word.clone(),
+                    word_to_fetch,
                     reason: FetchReason::MissingRankList,
+                    round: state.round,
+                    puzzle_number: state.puzzle_number,
                 });
             }
 
             None
         }
 
+        /// 获取期间频道已被其他请求推进时，获取结果不再适用。
+        fn is_stale(&self, channel_id: &str, request: &FetchRequest) -> bool {
+            let state = self.states.get(channel_id);
+            match request.reason {
+                // 切换猜测模式会先建好还没有排名列表的占位状态
+                FetchReason::NewGame => state.is_some_and(|s| !s.is_placeholder()),
+                FetchReason::NewDay | FetchReason::NewRound => {
+                    state.is_none_or(|s| s.round != request.round || !s.is_finished)
+                }
+                FetchReason::MissingRankList => {
+                    state.is_none_or(|s| s.round != request.round || !s.words_rank_list.is_empty())
+                }
+                FetchReason::Prefetch => state.is_none_or(|s| {
+                    s.pending_round
+                        .as_ref()
+                        .is_some_and(|p| p.covers(request.puzzle_number))
+                }),
+            }
+        }
+
         pub fn commit_guess(
             &mut self,
             channel_id: &str,
@@ -170,56 +1001,112 @@ mod ciyi_game {
             guess_word: String,
             fetched_data: Option<FetchedData>,
         ) -> String {
-            if let Some(data) = fetched_data {
-                let rank_list = match data.result {
-                    Ok(list) => list,
-                    Err(e) => return format!("获取词语排名失败：{e}"),
-                };
+            if let Some(data) = fetched_data
+                && let Err(message) = self.apply_fetched(channel_id, data)
+            {
+                return message;
+            }
+            self.apply_guess(channel_id, user_id, username, guess_word)
+        }
 
-                match data.request.reason {
-                    FetchReason::NewGame => {
-                        let new_state = CiYiGameState {
-                            channel_id: channel_id.to_string(),
-                            target_word: data.request.word_to_fetch.clone(),
-                            last_start_time: Utc::now(),
-                            global_history: HashSet::from([data.request.word_to_fetch.clone()]),
-                            current_guesses: HashSet::new(),
-                            words_rank_list: rank_list,
-                            hints: Vec::new(),
-                            is_finished: false,
-                            direct_guess_enabled: p_config::config().plugin.direct_guess,
-                        };
-                        self.states.insert(channel_id.to_string(), new_state);
-                    }
-                    FetchReason::NewDay => {
-                        if let Some(state) = self.states.get_mut(channel_id) {
-                            state.hints.clear();
-                            state.current_guesses.clear();
-                            state.target_word = data.request.word_to_fetch.clone();
-                            state.global_history.insert(data.request.word_to_fetch);
-                            state.words_rank_list = rank_list;
-                            state.last_start_time = Utc::now();
-                            state.is_finished = false;
-                        }
+        /// 按获取原因应用排名列表，过期的结果直接丢弃。获取失败时返回给用户的提示。
+        fn apply_fetched(&mut self, channel_id: &str, data: FetchedData) -> Result<(), String> {
+            if self.is_stale(channel_id, &data.request) {
+                log::debug!(
+                    "Discarding stale ciyi fetch for {} in {channel_id}",
+                    data.request.word_to_fetch
+                );
+                return Ok(());
+            }
+            let rank_list = match data.result {
+                Ok(list) => list,
+                Err(e) => {
+                    log::error!(
+                        "Failed to fetch ciyi rank list for {}: {e}",
+                        data.request.word_to_fetch
+                    );
+                    return Err("词语排名暂时获取不到，请稍后再试".to_string());
+                }
+            };
+
+            match data.request.reason {
+                FetchReason::NewGame if self.states.contains_key(channel_id) => {
+                    if let Some(state) = self.states.get_mut(channel_id) {
+                        state.global_history.remove(&state.target_word);
+                        state
+                            .global_history
+                            .insert(data.request.word_to_fetch.clone());
+                        state.target_word = data.request.word_to_fetch;
+                        state.words_rank_list = rank_list;
+                        state.last_start_time = Utc::now();
+                        state.puzzle_number = data.request.puzzle_number;
+                    }
+                }
+                FetchReason::NewGame => {
+                    let new_state = CiYiGameState {
+                        channel_id: channel_id.to_string(),
+                        target_word: data.request.word_to_fetch.clone(),
+                        last_start_time: Utc::now(),
+                        global_history: HashSet::from([data.request.word_to_fetch.clone()]),
+                        guesses: Vec::new(),
+                        words_rank_list: rank_list,
+                        hints: Vec::new(),
+                        hints_used: 0,
+                        is_finished: false,
+                        direct_guess_enabled: p_config::config().plugin.direct_guess,
+                        pending_round: None,
+                        round: 0,
+                        puzzle_number: data.request.puzzle_number,
+                        give_up_votes: HashSet::new(),
+                    };
+                    self.states.insert(channel_id.to_string(), new_state);
+                }
+                FetchReason::NewDay | FetchReason::NewRound => {
+                    if let Some(state) = self.states.get_mut(channel_id) {
+                        state.start_round(
+                            data.request.word_to_fetch,
+                            rank_list,
+                            data.request.puzzle_number,
+                        );
                     }
-                    FetchReason::MissingRankList => {
-                        if let Some(state) = self.states.get_mut(channel_id) {
+                }
+                FetchReason::MissingRankList => {
+                    if let Some(state) = self.states.get_mut(channel_id) {
+                        if state.target_word == data.request.word_to_fetch {
                             state.words_rank_list = rank_list;
+                        } else {
+                            state.start_round(
+                                data.request.word_to_fetch,
+                                rank_list,
+                                data.request.puzzle_number,
+                            );
                         }
                     }
                 }
+                FetchReason::Prefetch => {}
             }
+            Ok(())
+        }
 
+        fn apply_guess(
+            &mut self,
+            channel_id: &str,
+            user_id: &str,
+            username: &str,
+            guess_word: String,
+        ) -> String {
             let state = match self.states.get_mut(channel_id) {
                 Some(s) => s,
                 None => return "游戏尚未开始，请重试".to_string(),
             };
 
+            state.roll_over();
+
             if state.is_finished {
-                return "每天只能玩一次哦！".to_string();
+                return finished_message(&self.round_records, channel_id);
             }
 
-            if state.current_guesses.contains(&guess_word) {
+            if state.has_guessed(&guess_word) {
                 return format!("{guess_word} 已猜过");
             }
 
@@ -227,23 +1114,75 @@ mod ciyi_game {
                 return format!("{guess_word} 不在词库中");
             }
 
-            state.current_guesses.insert(guess_word.clone());
+            // 没有排名列表时无从比较，不记录这次猜测
+            if state.words_rank_list.is_empty() {
+                return "词语排名暂时获取不到，请稍后再试".to_string();
+            }
+
+            let position = state.words_rank_list.iter().position(|w| w == &guess_word);
+            state.guesses.push(GuessRecord {
+                user_id: user_id.to_string(),
+                username: username.to_string(),
+                word: guess_word.clone(),
+                rank: position.map(|index| index + 1),
+                timestamp: Utc::now(),
+            });
+            let stats = self.guess_stats.entry(user_id.to_string()).or_default();
+            stats.guesses += 1;
+            if let Some(index) = position {
+                stats.ranked_guesses += 1;
+                stats.rank_sum += index as u64 + 1;
+            }
 
             if guess_word == state.target_word {
-                state.is_finished = true;
+                let first_of_day =
+                    rounds_on_day(&self.round_records, channel_id, state.last_start_time) == 0;
+                let points = state.win_points(user_id);
+                self.round_records.push(state.finish(RoundOutcome::Solved));
                 self.win_records.push(WinRecord {
                     user_id: user_id.to_string(),
                     username: username.to_string(),
                     channel_id: channel_id.to_string(),
                     timestamp: Utc::now(),
+                    first_of_day,
+                    hints_used: state.hints_used,
+                    points,
+                    guess_count: Some(state.guesses.len()),
                 });
-                format!(
-                    "恭喜你猜对了！\n答案：{}\n猜测：{} 次",
+                let mut message = format!(
+                    "{}恭喜你猜对了！\n答案：{}\n猜测：{} 次\n得分：{points}",
+                    state.puzzle_title(),
                     state.target_word,
-                    state.current_guesses.len()
-                )
+                    state.guesses.len()
+                );
+                if state.hints_used > 0 {
+                    message.push_str(&format!("\n提示：{} 次", state.hints_used));
+                }
+
+                let assist_config = &p_config::config().assist;
+                if assist_config.enabled {
+                    let assists = state.assists(user_id, assist_config.top_k);
+                    if !assists.is_empty() {
+                        let names: Vec<String> = assists
+                            .iter()
+                            .map(|(guess, rank)| format!("{} #{rank}", guess.username))
+                            .collect();
+                        message.push_str(&format!("\n助攻：{}", names.join("、")));
+                    }
+                    let now = Utc::now();
+                    self.assist_records
+                        .extend(assists.into_iter().map(|(guess, rank)| AssistRecord {
+                            user_id: guess.user_id.clone(),
+                            username: guess.username.clone(),
+                            channel_id: channel_id.to_string(),
+                            timestamp: now,
+                            best_rank: rank,
+                            points: assist_config.points,
+                        }));
+                }
+                message
             } else {
-                if let Some(index) = state.words_rank_list.iter().position(|w| w == &guess_word) {
+                if let Some(index) = position {
                     let rank = index + 1;
                     let prev_char = state
                         .words_rank_list
@@ -262,45 +1201,483 @@ mod ciyi_game {
                     });
                 }
                 state.hints.sort_unstable();
-                let hints_str: String = state
-                    .hints
+                format!("{}{}...", state.puzzle_title(), state.hint_lines())
+            }
+        }
+
+        /// 「词意进度」：不猜测也能查看本局的提示与进展。
+        pub fn get_progress(&self, channel_id: &str) -> String {
+            let Some(state) = self.states.get(channel_id) else {
+                return "游戏尚未开始".to_string();
+            };
+            let (status, ended_at) = if state.is_finished {
+                // 用时停在本局结束时
+                let ended_at = self
+                    .round_records
                     .iter()
-                    .take(p_config::config().plugin.history_display)
-                    .enumerate()
-                    .map(|(i, hint)| format!("{}. {}\n", i + 1, hint.text))
-                    .collect();
-                format!("{hints_str}...")
+                    .rev()
+                    .find(|r| r.channel_id == channel_id && r.round == state.round)
+                    .map(|r| r.ended_at)
+                    .or_else(|| state.guesses.last().map(|g| g.timestamp));
+                ("本局已结束", ended_at)
+            } else {
+                ("本局进行中", None)
+            };
+            let mut message = format!(
+                "{}{status}\n猜测：{} 次，{} 人参与\n用时：{}",
+                state.puzzle_title(),
+                state.guesses.len(),
+                state.participant_count(),
+                format_duration(ended_at.unwrap_or_else(Utc::now) - state.last_start_time)
+            );
+            if let Some(closest) = state.closest_guess()
+                && let Some(rank) = closest.rank
+            {
+                message.push_str(&format!("\n最接近：#{rank}（{}）\n\n", closest.username));
+                message.push_str(&state.hint_lines());
+                message.push_str("...");
+            }
+            message
+        }
+
+        /// 「词意回放」：本局结束后按顺序列出每次猜测。
+        pub fn get_replay(&self, channel_id: &str) -> String {
+            let Some(state) = self.states.get(channel_id) else {
+                return "游戏尚未开始".to_string();
+            };
+            if !state.is_finished {
+                return "本局结束后才能回放".to_string();
+            }
+            let timezone = p_config::config().schedule.cycle(channel_id).timezone;
+            let mut message = format!(
+                "{}本局回放\n答案：{}",
+                state.puzzle_title(),
+                state.target_word
+            );
+            for (i, guess) in state.guesses.iter().take(MAX_REPLAY_LINES).enumerate() {
+                let rank = guess
+                    .rank
+                    .map_or_else(|| "不在排名中".to_string(), |rank| format!("#{rank}"));
+                message.push_str(&format!(
+                    "\n{}. {} {} {} {rank}",
+                    i + 1,
+                    timezone.to_local(guess.timestamp).format("%H:%M"),
+                    guess.username,
+                    guess.word
+                ));
+            }
+            if state.guesses.len() > MAX_REPLAY_LINES {
+                message.push_str(&format!("\n...（共 {} 次猜测）", state.guesses.len()));
+            }
+            message
+        }
+
+        /// 「词意新局」：本局已结束且今天还有局数时换一个词语，换日或新频道时与猜测一样开始。
+        pub fn prepare_new_round(&self, channel_id: &str) -> Option<FetchRequest> {
+            let Some(state) = self.states.get(channel_id) else {
+                return self.prepare_guess(channel_id);
+            };
+            if !state.is_finished || state.is_new_day() {
+                return self.prepare_guess(channel_id);
+            }
+            if !may_start_extra_round(&self.round_records, channel_id)
+                || state
+                    .pending_round
+                    .as_ref()
+                    .is_some_and(|p| p.puzzle_number.is_none())
+            {
+                return None;
+            }
+            // 全局每日模式的加赛局不占用题号
+            self.choose_target(Some(state), None)
+                .map(|word| FetchRequest {
+                    word_to_fetch: word,
+                    reason: FetchReason::NewRound,
+                    round: state.round,
+                    puzzle_number: None,
+                })
+        }
+
+        pub fn commit_new_round(
+            &mut self,
+            channel_id: &str,
+            fetched_data: Option<FetchedData>,
+        ) -> String {
+            let round_before = self.states.get(channel_id).map(|s| s.round);
+            if let Some(data) = fetched_data
+                && let Err(message) = self.apply_fetched(channel_id, data)
+            {
+                return message;
+            }
+            let Some(state) = self.states.get_mut(channel_id) else {
+                return "游戏尚未开始，请重试".to_string();
+            };
+
+            state.roll_over();
+            if state.is_finished
+                && may_start_extra_round(&self.round_records, channel_id)
+                && let Some(pending) = state.pending_round.take_if(|p| p.puzzle_number.is_none())
+            {
+                state.start_round(pending.target_word, pending.words_rank_list, None);
+            }
+
+            if state.is_finished {
+                finished_message(&self.round_records, channel_id)
+            } else if round_before != Some(state.round) {
+                format!("{}新的一局开始了！", state.puzzle_title())
+            } else {
+                "本局还没有结束".to_string()
+            }
+        }
+
+        /// 「词意提示」：按顺序给出越来越强的提示。每局次数有限，
+        /// 每次提示前需要本局再猜够 `guesses_per_hint` 次，并且距开局超过 `unlock_minutes` 分钟。
+        pub fn take_hint(&mut self, channel_id: &str) -> String {
+            self.take_hint_with(&p_config::config().hint, channel_id)
+        }
+
+        fn take_hint_with(&mut self, config: &HintConfig, channel_id: &str) -> String {
+            if config.max_per_round == 0 {
+                return "提示功能未开启".to_string();
+            }
+            let Some(state) = self.states.get_mut(channel_id) else {
+                return "游戏尚未开始".to_string();
+            };
+            if state.is_finished {
+                return "本局已经结束".to_string();
+            }
+            if state.words_rank_list.is_empty() {
+                return "请先猜一次词语再获取提示".to_string();
+            }
+
+            let clues = state.clues(&config.ranks);
+            let limit = config.max_per_round.min(clues.len());
+            let used = state.hints_used as usize;
+            if used >= limit {
+                return "本局的提示已经用完了".to_string();
+            }
+            let required_guesses = config.guesses_per_hint * (used + 1);
+            let guesses = state.guesses.len();
+            if guesses < required_guesses {
+                return format!("再猜 {} 次才能获取提示", required_guesses - guesses);
+            }
+            let unlock_at =
+                state.last_start_time + chrono::Duration::minutes(config.unlock_minutes as i64);
+            let now = Utc::now();
+            if now < unlock_at {
+                let minutes = (unlock_at - now).num_minutes() + 1;
+                return format!("{minutes} 分钟后才能获取提示");
+            }
+
+            state.hints_used += 1;
+            format!(
+                "{}提示 {}/{limit}：{}",
+                state.puzzle_title(),
+                state.hints_used,
+                clues[used]
+            )
+        }
+
+        /// 放弃本轮：公布答案与排名靠前的词语，不记胜场。
+        /// 投票模式下票数不足时只记下这一票，管理员可直接放弃。
+        pub fn give_up(&mut self, channel_id: &str, user_id: &str, is_admin: bool) -> String {
+            self.give_up_with(&p_config::config().give_up, channel_id, user_id, is_admin)
+        }
+
+        fn give_up_with(
+            &mut self,
+            config: &GiveUpConfig,
+            channel_id: &str,
+            user_id: &str,
+            is_admin: bool,
+        ) -> String {
+            let Some(state) = self.states.get_mut(channel_id) else {
+                return "游戏尚未开始".to_string();
+            };
+            if state.is_finished {
+                return "本轮已经结束".to_string();
+            }
+
+            match config.mode {
+                GiveUpMode::Anyone => {}
+                GiveUpMode::Admin => {
+                    if !is_admin {
+                        return "只有管理员可以放弃本轮".to_string();
+                    }
+                }
+                GiveUpMode::Vote => {
+                    state.give_up_votes.insert(user_id.to_string());
+                    let votes = state.give_up_votes.len();
+                    if !is_admin && votes < config.votes_required {
+                        return format!("已有 {votes}/{} 人同意放弃本轮", config.votes_required);
+                    }
+                }
+            }
+
+            let record = state.finish(RoundOutcome::GaveUp);
+            let closest: String = state
+                .words_rank_list
+                .iter()
+                .filter(|w| **w != state.target_word)
+                .take(config.reveal_top)
+                .enumerate()
+                .map(|(i, w)| format!("\n{}. {w}", i + 1))
+                .collect();
+            let mut message = format!(
+                "{}本轮已放弃\n答案：{}\n猜测：{} 次",
+                state.puzzle_title(),
+                state.target_word,
+                record.guess_count
+            );
+            if !closest.is_empty() {
+                message.push_str("\n\n最接近的词语：");
+                message.push_str(&closest);
+            }
+            self.round_records.push(record);
+            message
+        }
+
+        /// 到换题时间仍无人猜中的轮次按配置公布答案并结束，返回结束的频道与需要发送的消息。
+        /// 没有人猜过的轮次直接结束，不发送消息。
+        pub fn expire_unsolved(&mut self) -> Vec<(String, Option<String>)> {
+            if p_config::config().schedule.unsolved != UnsolvedPolicy::Reveal {
+                return Vec::new();
+            }
+            self.expire_stale_rounds()
+        }
+
+        fn expire_stale_rounds(&mut self) -> Vec<(String, Option<String>)> {
+            let mut expired = Vec::new();
+            for state in self.states.values_mut() {
+                if state.is_finished || !state.is_new_day() {
+                    continue;
+                }
+                let record = state.finish(RoundOutcome::Expired);
+                let notice = (record.guess_count > 0).then(|| {
+                    let mut message = format!(
+                        "{}到换题时间了，本轮无人猜出\n答案：{}",
+                        state.puzzle_title(),
+                        state.target_word
+                    );
+                    if let Some(closest) = state.hints.iter().min() {
+                        message.push_str("\n最接近的猜测：");
+                        message.push_str(&closest.text);
+                    }
+                    message
+                });
+                expired.push((state.channel_id.clone(), notice));
+                self.round_records.push(record);
+            }
+            if !expired.is_empty() {
+                self.mark_dirty();
+            }
+            expired
+        }
+
+        /// 为已结束、有人猜对过的赛季存档各频道和全服的最终排名，已存档的赛季不再变动。
+        pub fn archive_seasons(&mut self) {
+            let config = p_config::config();
+            if !config.season.enabled {
+                return;
+            }
+            let schedule = &config.schedule;
+            let current = open_season(schedule, config.season.period, Utc::now());
+            if self.checked_season == Some(current) {
+                return;
+            }
+            self.checked_season = Some(current);
+
+            let mut groups: HashMap<(Season, Option<&str>), Vec<&WinRecord>> = HashMap::new();
+            for record in &self.win_records {
+                let day = schedule
+                    .cycle(&record.channel_id)
+                    .game_day(record.timestamp);
+                let season = Season::containing(config.season.period, day);
+                if season.first_day() >= current.first_day() {
+                    continue;
+                }
+                for channel_id in [Some(record.channel_id.as_str()), None] {
+                    groups.entry((season, channel_id)).or_default().push(record);
+                }
+            }
+
+            let mut archives: Vec<SeasonArchive> = groups
+                .into_iter()
+                .filter(|((season, channel_id), _)| {
+                    !self.season_archives.iter().any(|archive| {
+                        archive.season == *season && archive.channel_id.as_deref() == *channel_id
+                    })
+                })
+                .map(|((season, channel_id), records)| SeasonArchive {
+                    season,
+                    channel_id: channel_id.map(str::to_string),
+                    sort: config.score.sort_by,
+                    standings: rank_scores(records.into_iter(), config.score.sort_by),
+                    archived_at: Utc::now(),
+                })
+                .collect();
+            if archives.is_empty() {
+                return;
+            }
+            archives.sort_by(|a, b| {
+                (a.season.first_day(), &a.channel_id).cmp(&(b.season.first_day(), &b.channel_id))
+            });
+            log::info!("Archived {} ciyi season standings", archives.len());
+            self.season_archives.extend(archives);
+            self.mark_dirty();
+        }
+
+        /// 「词意赛季」：不带名称时列出往届赛季，带名称时显示该赛季的最终排名。
+        pub fn get_season_archive(&self, channel_id: Option<&str>, name: Option<&str>) -> String {
+            let archives: Vec<&SeasonArchive> = self
+                .season_archives
+                .iter()
+                .filter(|archive| archive.channel_id.as_deref() == channel_id)
+                .collect();
+
+            let Some(name) = name else {
+                if archives.is_empty() {
+                    return "还没有结束的赛季".to_string();
+                }
+                let mut lines = vec!["往届赛季：".to_string()];
+                for archive in archives.iter().rev() {
+                    let mut line = archive.season.name();
+                    if let Some(champion) = archive.standings.first() {
+                        line.push_str(&format!(" 冠军 {}", champion.username));
+                    }
+                    lines.push(line);
+                }
+                return lines.join("\n");
+            };
+
+            // 按存档自己的周期解析，「2026q1」「2026-1」也能找到对应赛季
+            let Some(archive) = archives
+                .iter()
+                .find(|archive| Season::parse(archive.season.period, name) == Some(archive.season))
+            else {
+                return format!("没有找到赛季 {name} 的存档");
+            };
+            format!(
+                "赛季 {}（{} ~ {}）\n{}",
+                archive.season.name(),
+                archive.season.first_day(),
+                archive.season.last_day(),
+                format_standings(&archive.standings, archive.sort)
+            )
+        }
+
+        /// 同一频道同时只进行一次获取，后来者等待进行中的获取完成。
+        pub fn fetch_lock(&mut self, channel_id: &str) -> Arc<tokio::sync::Mutex<()>> {
+            Arc::clone(self.fetch_locks.entry(channel_id.to_string()).or_default())
+        }
+
+        /// 排名列表未通过校验的词语不再被选为目标。
+        pub fn reject_word(&mut self, word: &str) {
+            self.rejected_words.insert(word.to_string());
+        }
+
+        pub fn channel_ids(&self) -> Vec<String> {
+            self.states.keys().cloned().collect()
+        }
+
+        pub fn prepare_prefetch(&self, channel_id: &str) -> Option<FetchRequest> {
+            let state = self.states.get(channel_id)?;
+            // 全局模式预取本频道还没玩到的下一题；加赛局不占用题号，按最近玩过的题号算
+            let last_puzzle = state.puzzle_number.or_else(|| {
+                self.round_records
+                    .iter()
+                    .rev()
+                    .filter(|r| r.channel_id == channel_id)
+                    .find_map(|r| r.puzzle_number)
+            });
+            let puzzle = current_puzzle_number()
+                .map(|today| last_puzzle.map_or(today, |n| (n + 1).max(today)));
+            if state
+                .pending_round
+                .as_ref()
+                .is_some_and(|p| p.covers(puzzle))
+            {
+                return None;
+            }
+            self.choose_target(Some(state), puzzle)
+                .map(|word| FetchRequest {
+                    word_to_fetch: word,
+                    reason: FetchReason::Prefetch,
+                    round: state.round,
+                    puzzle_number: puzzle,
+                })
+        }
+
+        pub fn commit_prefetch(&mut self, channel_id: &str, data: FetchedData) {
+            if self.is_stale(channel_id, &data.request) {
+                return;
+            }
+            let word = data.request.word_to_fetch;
+            let rank_list = match data.result {
+                Ok(list) => list,
+                Err(e) => {
+                    log::warn!("Failed to prefetch ciyi rank list for {word}: {e}");
+                    if p_rank::is_unusable_target(e.as_ref()) {
+                        self.reject_word(&word);
+                    }
+                    return;
+                }
+            };
+            let Some(state) = self.states.get_mut(channel_id) else {
+                return;
+            };
+            if data.request.puzzle_number.is_none() && state.global_history.contains(&word) {
+                return;
             }
+            state.pending_round = Some(PendingRound {
+                target_word: word,
+                words_rank_list: rank_list,
+                fetched_at: Utc::now(),
+                puzzle_number: data.request.puzzle_number,
+            });
+        }
+
+        pub fn is_finished(&self, channel_id: &str) -> bool {
+            self.states.get(channel_id).is_some_and(|s| s.is_finished)
         }
 
         pub fn get_direct_guess_status(&mut self, channel_id: &str) -> bool {
             let state = self.states.get(channel_id);
             match state {
-                Some(s) => {
-                    (s.is_new_day_in_china_timezone() || !s.is_finished) && s.direct_guess_enabled
-                }
+                Some(s) => (s.is_new_day() || !s.is_finished) && s.direct_guess_enabled,
                 None => p_config::config().plugin.direct_guess,
             }
         }
 
         pub fn toggle_direct_guess_mode(&mut self, channel_id: &str) -> String {
-            let state = self
-                .states
-                .entry(channel_id.to_string())
-                .or_insert_with(|| {
-                    let target = &QUESTION_WORDS[fastrand::usize(..QUESTION_WORDS.len())];
+            if !self.states.contains_key(channel_id) {
+                let puzzle = current_puzzle_number();
+                let Some(target) = self.choose_target(None, puzzle) else {
+                    return "题库中没有可用的词语".to_string();
+                };
+                self.states.insert(
+                    channel_id.to_string(),
                     CiYiGameState {
                         channel_id: channel_id.to_string(),
-                        target_word: target.to_string(),
+                        target_word: target.clone(),
                         last_start_time: Utc::now(),
-                        global_history: HashSet::from([target.to_string()]),
-                        current_guesses: HashSet::new(),
+                        global_history: HashSet::from([target]),
+                        guesses: Vec::new(),
                         words_rank_list: Vec::new(),
                         hints: Vec::new(),
+                        hints_used: 0,
                         is_finished: false,
                         direct_guess_enabled: p_config::config().plugin.direct_guess,
-                    }
-                });
+                        pending_round: None,
+                        round: 0,
+                        puzzle_number: puzzle,
+                        give_up_votes: HashSet::new(),
+                    },
+                );
+            }
+            let Some(state) = self.states.get_mut(channel_id) else {
+                return "游戏尚未开始，请重试".to_string();
+            };
 
             state.direct_guess_enabled = !state.direct_guess_enabled;
 
@@ -311,103 +1688,1090 @@ mod ciyi_game {
             }
         }
 
-        pub fn get_global_leaderboard(&self) -> String {
-            self.generate_leaderboard(self.win_records.iter())
+        pub fn get_global_leaderboard(&self, query: &LeaderboardQuery, user_id: &str) -> String {
+            let today = p_config::config()
+                .schedule
+                .default_cycle()
+                .game_day(Utc::now());
+            self.windowed_leaderboard(self.win_records.iter(), query, user_id, today)
         }
 
-        pub fn get_channel_leaderboard(&self, channel_id: &str) -> String {
+        pub fn get_channel_leaderboard(
+            &self,
+            channel_id: &str,
+            query: &LeaderboardQuery,
+            user_id: &str,
+        ) -> String {
+            let today = p_config::config()
+                .schedule
+                .cycle(channel_id)
+                .game_day(Utc::now());
             let channel_records = self
                 .win_records
                 .iter()
                 .filter(|r| r.channel_id == channel_id);
-            self.generate_leaderboard(channel_records)
+            self.windowed_leaderboard(channel_records, query, user_id, today)
         }
 
-        fn generate_leaderboard<'a, I>(&self, records: I) -> String
+        /// 每条记录按所在频道的游戏日归入统计范围。
+        fn windowed_leaderboard<'a, R, I>(
+            &self,
+            records: I,
+            query: &LeaderboardQuery,
+            user_id: &str,
+            today: NaiveDate,
+        ) -> String
         where
-            I: Iterator<Item = &'a WinRecord>,
+            R: ScoreRecord + 'a,
+            I: Iterator<Item = &'a R>,
         {
-            let mut scores: HashMap<String, UserScore> = HashMap::new();
-            for record in records {
-                let user_score =
-                    scores
-                        .entry(record.user_id.clone())
-                        .or_insert_with(|| UserScore {
-                            user_id: record.user_id.clone(),
-                            username: record.username.clone(),
-                            score: 0,
-                        });
-                user_score.username = record.username.clone();
-                user_score.score += 1;
+            let schedule = &p_config::config().schedule;
+            let bounds = query.window.bounds(today);
+            let records = records.filter(|r| {
+                bounds.is_none_or(|(start, end)| {
+                    let day = schedule.cycle(r.channel_id()).game_day(r.timestamp());
+                    start <= day && day <= end
+                })
+            });
+            let leaderboard = self.generate_leaderboard(records, query, user_id);
+            match query.window.title(today) {
+                Some(title) => format!("{title}\n{leaderboard}"),
+                None => leaderboard,
+            }
+        }
+
+        /// 「词意我的」：用户的胜场、猜测与连胜统计。
+        pub fn get_user_stats(&self, user_id: &str, username: &str) -> String {
+            let wins: Vec<&WinRecord> = self
+                .win_records
+                .iter()
+                .filter(|r| r.user_id == user_id)
+                .collect();
+            let stats = self.guess_stats.get(user_id);
+            if wins.is_empty() && stats.is_none() {
+                return "你还没有参与过词意游戏".to_string();
             }
 
-            if scores.is_empty() {
-                return "当前还没有人猜对过哦！".to_string();
+            let mut lines = vec![
+                format!("{username} 的词意数据"),
+                format!("猜对：{} 次", wins.len()),
+            ];
+
+            let mut per_channel: Vec<(&str, usize)> = Vec::new();
+            for record in &wins {
+                match per_channel
+                    .iter_mut()
+                    .find(|(c, _)| *c == record.channel_id)
+                {
+                    Some((_, count)) => *count += 1,
+                    None => per_channel.push((&record.channel_id, 1)),
+                }
+            }
+            per_channel.sort_by_key(|&(_, count)| Reverse(count));
+            for (channel_id, count) in per_channel {
+                lines.push(format!("  群 {channel_id}：{count} 次"));
             }
 
-            let mut sorted_scores: Vec<UserScore> = scores.into_values().collect();
-            sorted_scores.sort_by(|a, b| b.score.cmp(&a.score));
+            if let Some(stats) = stats {
+                let mut line = format!("猜测：{} 次", stats.guesses);
+                if stats.ranked_guesses > 0 {
+                    let average = stats.rank_sum as f64 / stats.ranked_guesses as f64;
+                    line.push_str(&format!("，平均排名 #{average:.1}"));
+                }
+                lines.push(line);
+            }
 
-            let leaderboard_str: String = sorted_scores
-                .iter()
-                .take(p_config::config().plugin.rank_display)
-                .enumerate()
-                .map(|(index, user_score)| {
-                    format!(
-                        "{}. {} {}",
-                        index + 1,
-                        user_score.username,
-                        user_score.score
-                    )
-                })
-                .collect::<Vec<String>>()
-                .join("\n");
+            if let Some(best) = wins.iter().filter_map(|r| r.guess_count).min() {
+                lines.push(format!("最佳一局：{best} 次猜中"));
+            }
+
+            if !wins.is_empty() {
+                let schedule = &p_config::config().schedule;
+                let mut days: Vec<NaiveDate> = wins
+                    .iter()
+                    .map(|r| schedule.cycle(&r.channel_id).game_day(r.timestamp))
+                    .collect();
+                days.sort_unstable();
+                days.dedup();
+                let (current, longest) =
+                    win_streaks(&days, schedule.default_cycle().game_day(Utc::now()));
+                lines.push(format!("连胜：当前 {current} 天，最长 {longest} 天"));
+
+                let timezone = schedule.timezone;
+                let first = wins.iter().map(|r| r.timestamp).min();
+                let last = wins.iter().map(|r| r.timestamp).max();
+                if let (Some(first), Some(last)) = (first, last) {
+                    lines.push(format!(
+                        "首次猜对：{}",
+                        timezone.to_local(first).format("%Y-%m-%d")
+                    ));
+                    lines.push(format!(
+                        "最近猜对：{}",
+                        timezone.to_local(last).format("%Y-%m-%d")
+                    ));
+                }
+            }
 
-            leaderboard_str
+            lines.join("\n")
         }
-    }
 
-    pub async fn fetch_words_rank_list(word: &str) -> Result<Vec<String>, Box<dyn Error>> {
-        let url =
-            format!("https://ci-ying.oss-cn-zhangjiakou.aliyuncs.com/v1/ci-yi-list/{word}.txt");
-        let response = reqwest::get(&url).await?;
-        let response = response.error_for_status()?;
-        let body_text = response.text().await?;
-        let words_rank_list: Vec<String> = body_text
-            .trim()
-            .split('\n')
-            .filter(|s| !s.is_empty())
-            .map(String::from)
-            .collect();
-        Ok(words_rank_list)
-    }
-}
+        /// 「词意助攻榜」：当前频道的助攻次数与助攻积分，参数与排行榜相同。
+        pub fn get_assist_leaderboard(
+            &self,
+            channel_id: &str,
+            query: &LeaderboardQuery,
+            user_id: &str,
+        ) -> String {
+            let today = p_config::config()
+                .schedule
+                .cycle(channel_id)
+                .game_day(Utc::now());
+            let channel_records = self
+                .assist_records
+                .iter()
+                .filter(|r| r.channel_id == channel_id);
+            self.windowed_leaderboard(channel_records, query, user_id, today)
+        }
 
-mod p_command {
-    use kovi::toml;
-    use kovi::utils::load_toml_data;
-    use serde::{Deserialize, Serialize};
-    use std::error::Error;
-    use std::path::PathBuf;
-    use std::sync::OnceLock;
+        /// 列出第 `query.page` 页，查询者不在本页时在末尾附上其排名。
+        fn generate_leaderboard<'a, R, I>(
+            &self,
+            records: I,
+            query: &LeaderboardQuery,
+            user_id: &str,
+        ) -> String
+        where
+            R: ScoreRecord + 'a,
+            I: Iterator<Item = &'a R>,
+        {
+            let sorted_scores = rank_scores(records, query.sort);
+            if sorted_scores.is_empty() {
+                return R::EMPTY.to_string();
+            }
 
-    pub static COMMAND: OnceLock<CommandConfig> = OnceLock::new();
-    pub fn commands() -> &'static CommandConfig {
-        COMMAND.get().expect("Commands not initialized")
+            let page_size = p_config::config().plugin.rank_display.max(1);
+            let pages = sorted_scores.len().div_ceil(page_size);
+            if query.page > pages {
+                return format!("排行榜只有 {pages} 页");
+            }
+            let shown = (query.page - 1) * page_size..query.page * page_size;
+
+            let mut lines: Vec<String> = sorted_scores
+                .iter()
+                .enumerate()
+                .skip(shown.start)
+                .take(page_size)
+                .map(|(index, user_score)| R::standing_line(index + 1, user_score, query.sort))
+                .collect();
+            if pages > 1 {
+                lines.push(format!("第 {}/{pages} 页", query.page));
+            }
+            match sorted_scores.iter().position(|s| s.user_id == user_id) {
+                Some(index) if shown.contains(&index) => {}
+                Some(index) => lines.push(format!(
+                    "你的排名：{}",
+                    R::standing_line(index + 1, &sorted_scores[index], query.sort)
+                )),
+                None => lines.push("你还没有上榜".to_string()),
+            }
+            lines.join("\n")
+        }
     }
 
-    pub const DEFAULT_COMMANDS_STR: &str = r#"
-# 定义插件的指令。每个 [[command]] 块代表一种功能及其关联的触发词。
-# function: 功能的内部描述，用于代码逻辑判断。
-# commands: 用户可以输入的指令列表。
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::p_rank::{MemoryRankListProvider, RankListProvider};
+
+        fn fetched(request: FetchRequest, rest: &[&str]) -> FetchedData {
+            let mut list = vec![request.word_to_fetch.clone()];
+            list.extend(rest.iter().map(|w| w.to_string()));
+            FetchedData {
+                result: Ok(list),
+                request,
+            }
+        }
 
-[[command]]
-function = "查看插件指令列表"
-commands = ["词意帮助", "词意指令", "词意指令列表", "词意帮助列表"]
+        /// 通过内存来源获取目标词语的排名列表：目标词语在首位，其后是另外两个词语。
+        fn fetched_from_memory(request: FetchRequest) -> (FetchedData, Vec<String>) {
+            let target = request.word_to_fetch.clone();
+            let mut list = vec![target.clone()];
+            list.extend(
+                ["企业", "东西", "公司"]
+                    .iter()
+                    .filter(|w| **w != target)
+                    .take(2)
+                    .map(|w| w.to_string()),
+            );
+            let provider = MemoryRankListProvider::new(HashMap::from([(target, list.clone())]));
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let result = runtime.block_on(provider.fetch(&request.word_to_fetch));
+            (FetchedData { request, result }, list)
+        }
 
-[[command]]
-function = "查看词意游戏规则"
-commands = ["词意玩法", "词意规则"]
+        #[test]
+        fn win_points_follow_the_score_weights() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let request = manager.prepare_guess("1").expect("new game");
+            let (data, list) = fetched_from_memory(request);
+            manager.commit_guess("1", "a", "A", list[1].clone(), Some(data));
+            manager.commit_guess("1", "b", "B", list[2].clone(), None);
+            manager.commit_guess("1", "a", "A", list[0].clone(), None);
+
+            // 100 - 0.5 × 3 + 30 × 2/3 + 20 / 2
+            assert_eq!(manager.win_records[0].points, 129);
+            let state = manager.states.get_mut("1").unwrap();
+            // 100 - 0.5 × 3 + 30 × 1/3 + 20 / 3
+            assert_eq!(state.win_points("b"), 115);
+            state.hints_used = 2;
+            assert_eq!(state.win_points("a"), 99);
+            state.hints_used = 10;
+            assert_eq!(state.win_points("a"), 10);
+        }
+
+        #[test]
+        fn win_streaks_count_consecutive_days() {
+            let day = |d| NaiveDate::from_ymd_opt(2026, 2, d).unwrap();
+            let days = [day(1), day(2), day(3), day(5), day(6)];
+            assert_eq!(win_streaks(&days, day(6)), (2, 3));
+            // 今天还没猜对，到昨天为止的连胜仍然有效
+            assert_eq!(win_streaks(&days, day(7)), (2, 3));
+            assert_eq!(win_streaks(&days, day(8)), (0, 3));
+            assert_eq!(win_streaks(&[], day(8)), (0, 0));
+
+            // 跨月
+            let days = [
+                day(27),
+                day(28),
+                NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+            ];
+            let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+            assert_eq!(win_streaks(&days, today), (3, 3));
+        }
+
+        #[test]
+        fn time_window_bounds_cross_week_month_and_timezone_edges() {
+            p_config::init_default();
+            let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+            // 周一当天即为起点，周日回到本周一，可跨月、跨年
+            assert_eq!(
+                TimeWindow::Week.bounds(date(2026, 3, 2)),
+                Some((date(2026, 3, 2), date(2026, 3, 2)))
+            );
+            assert_eq!(
+                TimeWindow::Week.bounds(date(2026, 3, 1)),
+                Some((date(2026, 2, 23), date(2026, 3, 1)))
+            );
+            assert_eq!(
+                TimeWindow::Week.bounds(date(2027, 1, 1)),
+                Some((date(2026, 12, 28), date(2027, 1, 1)))
+            );
+            assert_eq!(
+                TimeWindow::Month.bounds(date(2026, 3, 1)),
+                Some((date(2026, 3, 1), date(2026, 3, 1)))
+            );
+            assert_eq!(
+                TimeWindow::Month.bounds(date(2026, 2, 28)),
+                Some((date(2026, 2, 1), date(2026, 2, 28)))
+            );
+            assert_eq!(TimeWindow::All.bounds(date(2026, 3, 1)), None);
+
+            // UTC 仍是 2 月 28 日，上海已是 3 月 1 日，按游戏日归入 3 月
+            let cycle = p_config::config().schedule.default_cycle();
+            let instant = "2026-02-28T16:30:00Z".parse::<DateTime<Utc>>().unwrap();
+            let day = cycle.game_day(instant);
+            assert_eq!(day, date(2026, 3, 1));
+            let (start, end) = TimeWindow::Month.bounds(day).unwrap();
+            assert!(start <= day && day <= end);
+            assert_eq!(start, date(2026, 3, 1));
+        }
+
+        #[test]
+        fn round_limit_counts_only_the_channels_current_day() {
+            p_config::init_default();
+            // 上海时间 2026-03-01 12:00
+            let now = "2026-03-01T04:00:00Z".parse::<DateTime<Utc>>().unwrap();
+            let round = |channel_id: &str, hours_ago: i64| RoundRecord {
+                channel_id: channel_id.into(),
+                round: 0,
+                puzzle_number: None,
+                target_word: "企业".into(),
+                outcome: RoundOutcome::Solved,
+                guess_count: 1,
+                started_at: now - chrono::Duration::hours(hours_ago),
+                ended_at: now,
+            };
+            // 13 小时前是前一天，不计入今天
+            let records = [round("1", 1), round("1", 11), round("1", 13), round("2", 1)];
+
+            assert!(!within_round_limit(2, &records, "1", now));
+            assert!(within_round_limit(3, &records, "1", now));
+            assert!(within_round_limit(0, &records, "1", now));
+            assert!(!within_round_limit(1, &records, "2", now));
+            assert!(within_round_limit(1, &records, "3", now));
+            let tomorrow = now + chrono::Duration::days(1);
+            assert!(within_round_limit(1, &records, "1", tomorrow));
+        }
+
+        #[test]
+        fn fetch_started_before_a_new_round_is_stale() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let request = manager.prepare_guess("1").expect("new game");
+            let target = request.word_to_fetch.clone();
+            manager.commit_guess("1", "a", "A", target.clone(), Some(fetched(request, &[])));
+            manager.states.get_mut("1").unwrap().last_start_time -= chrono::Duration::days(1);
+
+            // 两个请求都在换日前开始获取，先提交的开始了新的一局
+            let slow = manager.prepare_guess("1").expect("new day");
+            let fast = manager.prepare_guess("1").expect("new day");
+            assert!(matches!(slow.reason, FetchReason::NewDay));
+            let fast_word = fast.word_to_fetch.clone();
+            manager.commit_guess("1", "b", "B", target.clone(), Some(fetched(fast, &[])));
+            let round = manager.states["1"].round;
+
+            let slow_word = slow.word_to_fetch.clone();
+            let data = FetchedData {
+                result: Ok(vec![slow_word.clone(), fast_word.clone()]),
+                request: slow,
+            };
+            assert!(manager.is_stale("1", &data.request));
+            manager.commit_guess("1", "c", "C", target, Some(data));
+            let state = &manager.states["1"];
+            assert_eq!(state.round, round);
+            assert_eq!(state.target_word, fast_word);
+            assert_eq!(state.words_rank_list, vec![fast_word]);
+        }
+
+        #[test]
+        fn memory_provider_starts_a_new_game() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let request = manager.prepare_guess("1").expect("new game");
+            assert!(matches!(request.reason, FetchReason::NewGame));
+            let (data, list) = fetched_from_memory(request);
+
+            let reply = manager.commit_guess("1", "a", "A", list[1].clone(), Some(data));
+            assert!(reply.contains("#2"), "{reply}");
+            let state = &manager.states["1"];
+            assert_eq!(state.target_word, list[0]);
+            assert_eq!(state.words_rank_list, list);
+            assert!(!state.is_finished);
+            assert!(manager.prepare_guess("1").is_none());
+        }
+
+        #[test]
+        fn memory_provider_wrong_guess_keeps_round_open() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let request = manager.prepare_guess("1").expect("new game");
+            let (data, list) = fetched_from_memory(request);
+            manager.commit_guess("1", "a", "A", list[2].clone(), Some(data));
+
+            let reply = manager.commit_guess("1", "b", "B", list[2].clone(), None);
+            assert_eq!(reply, format!("{} 已猜过", list[2]));
+            let reply = manager.commit_guess("1", "b", "B", list[1].clone(), None);
+            assert!(reply.contains("#2") && reply.contains("#3"), "{reply}");
+
+            let state = &manager.states["1"];
+            assert_eq!(state.guesses.len(), 2);
+            assert_eq!(state.guesses[1].rank, Some(2));
+            assert!(!state.is_finished);
+            assert!(manager.win_records.is_empty());
+        }
+
+        #[test]
+        fn memory_provider_winning_guess_ends_round() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let request = manager.prepare_guess("1").expect("new game");
+            let (data, list) = fetched_from_memory(request);
+            manager.commit_guess("1", "a", "A", list[1].clone(), Some(data));
+
+            let reply = manager.commit_guess("1", "b", "B", list[0].clone(), None);
+            assert!(reply.contains("恭喜你猜对了"), "{reply}");
+            assert!(reply.contains("猜测：2 次"), "{reply}");
+            assert!(manager.states["1"].is_finished);
+            assert_eq!(manager.win_records.len(), 1);
+            assert_eq!(manager.win_records[0].user_id, "b");
+            assert_eq!(manager.win_records[0].guess_count, Some(2));
+            assert!(manager.prepare_guess("1").is_none());
+        }
+
+        /// 开始一局并由 a 猜第二接近的词语，返回排名列表。
+        fn started_round(manager: &mut CiYiGameManager) -> Vec<String> {
+            let request = manager.prepare_guess("1").expect("new game");
+            let (data, list) = fetched_from_memory(request);
+            manager.commit_guess("1", "a", "A", list[1].clone(), Some(data));
+            list
+        }
+
+        #[test]
+        fn user_stats_list_wins_per_channel() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let list = started_round(&mut manager);
+            manager.commit_guess("1", "b", "B", list[0].clone(), None);
+
+            let reply = manager.get_user_stats("b", "B");
+            let lines: Vec<&str> = reply.lines().collect();
+            assert_eq!(lines[..3], ["B 的词意数据", "猜对：1 次", "  群 1：1 次"]);
+            let reply = manager.get_user_stats("a", "A");
+            assert!(reply.contains("猜对：0 次"), "{reply}");
+            assert!(!reply.contains("群 1"), "{reply}");
+            assert_eq!(manager.get_user_stats("c", "C"), "你还没有参与过词意游戏");
+        }
+
+        #[test]
+        fn give_up_vote_counts_each_member_once() {
+            p_config::init_default();
+            let config = GiveUpConfig {
+                mode: GiveUpMode::Vote,
+                votes_required: 2,
+                reveal_top: 2,
+            };
+            let mut manager = CiYiGameManager::default();
+            let list = started_round(&mut manager);
+
+            let reply = manager.give_up_with(&config, "1", "a", false);
+            assert_eq!(reply, "已有 1/2 人同意放弃本轮");
+            let reply = manager.give_up_with(&config, "1", "a", false);
+            assert_eq!(reply, "已有 1/2 人同意放弃本轮");
+            assert!(!manager.states["1"].is_finished);
+
+            let reply = manager.give_up_with(&config, "1", "b", false);
+            assert_eq!(
+                reply,
+                format!(
+                    "本轮已放弃\n答案：{}\n猜测：1 次\n\n最接近的词语：\n1. {}\n2. {}",
+                    list[0], list[1], list[2]
+                )
+            );
+            assert!(manager.states["1"].is_finished);
+            let record = manager.round_records.last().unwrap();
+            assert_eq!(record.outcome, RoundOutcome::GaveUp);
+            assert_eq!(record.target_word, list[0]);
+            assert_eq!(record.guess_count, 1);
+            assert!(manager.win_records.is_empty());
+
+            let reply = manager.give_up_with(&config, "1", "c", false);
+            assert_eq!(reply, "本轮已经结束");
+            assert_eq!(manager.round_records.len(), 1);
+
+            // 管理员不必等待投票
+            let mut manager = CiYiGameManager::default();
+            started_round(&mut manager);
+            manager.give_up_with(&config, "1", "z", true);
+            assert!(manager.states["1"].is_finished);
+        }
+
+        #[test]
+        fn give_up_admin_and_anyone_modes() {
+            p_config::init_default();
+            let mut config = GiveUpConfig {
+                mode: GiveUpMode::Admin,
+                votes_required: 2,
+                reveal_top: 1,
+            };
+            let mut manager = CiYiGameManager::default();
+            assert_eq!(
+                manager.give_up_with(&config, "1", "a", true),
+                "游戏尚未开始"
+            );
+            let list = started_round(&mut manager);
+
+            let reply = manager.give_up_with(&config, "1", "a", false);
+            assert_eq!(reply, "只有管理员可以放弃本轮");
+            assert!(!manager.states["1"].is_finished);
+            let reply = manager.give_up_with(&config, "1", "z", true);
+            assert!(
+                reply.ends_with(&format!("最接近的词语：\n1. {}", list[1])),
+                "{reply}"
+            );
+            assert_eq!(
+                manager.round_records.last().unwrap().outcome,
+                RoundOutcome::GaveUp
+            );
+
+            config.mode = GiveUpMode::Anyone;
+            let mut manager = CiYiGameManager::default();
+            started_round(&mut manager);
+            let reply = manager.give_up_with(&config, "1", "b", false);
+            assert!(reply.starts_with("本轮已放弃"), "{reply}");
+            assert!(manager.states["1"].is_finished);
+            assert_eq!(manager.round_records.len(), 1);
+        }
+
+        #[test]
+        fn expired_rounds_reveal_the_answer_and_closest_guess() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let list = started_round(&mut manager);
+            manager.commit_guess("1", "b", "B", list[2].clone(), None);
+            let closest = manager.states["1"].hints.iter().min().unwrap().text.clone();
+            assert!(closest.contains(&list[1]), "{closest}");
+
+            // 频道 2 开始了一局，但没有人猜过
+            let request = manager.prepare_guess("2").expect("new game");
+            let (data, _) = fetched_from_memory(request);
+            manager.commit_guess("2", "a", "A", "ab".into(), Some(data));
+            assert!(manager.states["2"].guesses.is_empty());
+
+            assert!(manager.expire_stale_rounds().is_empty());
+            for state in manager.states.values_mut() {
+                state.last_start_time -= chrono::Duration::days(1);
+            }
+            let mut expired = manager.expire_stale_rounds();
+            expired.sort();
+            assert_eq!(
+                expired,
+                vec![
+                    (
+                        "1".to_string(),
+                        Some(format!(
+                            "到换题时间了，本轮无人猜出\n答案：{}\n最接近的猜测：{closest}",
+                            list[0]
+                        ))
+                    ),
+                    ("2".to_string(), None),
+                ]
+            );
+            assert!(manager.states.values().all(|state| state.is_finished));
+            let outcomes: Vec<_> = manager
+                .round_records
+                .iter()
+                .map(|r| (r.channel_id.as_str(), r.outcome, r.guess_count))
+                .collect();
+            assert_eq!(outcomes.len(), 2);
+            assert!(outcomes.contains(&("1", RoundOutcome::Expired, 2)));
+            assert!(outcomes.contains(&("2", RoundOutcome::Expired, 0)));
+            assert!(manager.win_records.is_empty());
+            assert!(manager.expire_stale_rounds().is_empty());
+
+            // 两个频道（包括没人猜过的）都会接着预取下一轮
+            for channel_id in ["1", "2"] {
+                assert!(manager.is_finished(channel_id));
+                let request = manager.prepare_prefetch(channel_id).expect("prefetch");
+                assert!(matches!(request.reason, FetchReason::Prefetch));
+            }
+        }
+
+        #[test]
+        fn hints_unlock_with_guesses_and_grow_stronger() {
+            p_config::init_default();
+            let config = HintConfig {
+                max_per_round: 3,
+                guesses_per_hint: 1,
+                unlock_minutes: 0,
+                ranks: vec![3, 2, 1],
+            };
+            let mut manager = CiYiGameManager::default();
+            let list = started_round(&mut manager);
+            let first = list[0].chars().next().unwrap();
+            let other = ALL_WORDS
+                .iter()
+                .find(|w| w.chars().count() == 2 && !list.contains(w))
+                .unwrap()
+                .clone();
+
+            // 名次 1 是目标词语本身，不作为提示
+            let reply = manager.take_hint_with(&config, "1");
+            assert_eq!(reply, format!("提示 1/3：排名第 3 的词语是「{}」", list[2]));
+            assert_eq!(
+                manager.take_hint_with(&config, "1"),
+                "再猜 1 次才能获取提示"
+            );
+            manager.commit_guess("1", "b", "B", list[2].clone(), None);
+            let reply = manager.take_hint_with(&config, "1");
+            assert_eq!(reply, format!("提示 2/3：排名第 2 的词语是「{}」", list[1]));
+            manager.commit_guess("1", "b", "B", other, None);
+            let reply = manager.take_hint_with(&config, "1");
+            assert_eq!(reply, format!("提示 3/3：目标词语的第一个字是「{first}」"));
+            assert_eq!(manager.take_hint_with(&config, "1"), "本局的提示已经用完了");
+            assert_eq!(manager.states["1"].hints_used, 3);
+
+            // 每局次数上限比可用的提示少时以上限为准
+            let config = HintConfig {
+                max_per_round: 1,
+                guesses_per_hint: 0,
+                ..config
+            };
+            let mut manager = CiYiGameManager::default();
+            started_round(&mut manager);
+            assert!(
+                manager
+                    .take_hint_with(&config, "1")
+                    .starts_with("提示 1/1：")
+            );
+            assert_eq!(manager.take_hint_with(&config, "1"), "本局的提示已经用完了");
+        }
+
+        #[test]
+        fn hints_wait_for_unlock_minutes_and_can_be_disabled() {
+            p_config::init_default();
+            let mut config = HintConfig {
+                max_per_round: 2,
+                guesses_per_hint: 0,
+                unlock_minutes: 30,
+                ranks: vec![2],
+            };
+            let mut manager = CiYiGameManager::default();
+            started_round(&mut manager);
+            assert_eq!(
+                manager.take_hint_with(&config, "1"),
+                "30 分钟后才能获取提示"
+            );
+            manager.states.get_mut("1").unwrap().last_start_time -= chrono::Duration::minutes(31);
+            assert!(
+                manager
+                    .take_hint_with(&config, "1")
+                    .starts_with("提示 1/2：")
+            );
+
+            config.max_per_round = 0;
+            assert_eq!(manager.take_hint_with(&config, "1"), "提示功能未开启");
+            assert_eq!(manager.states["1"].hints_used, 1);
+        }
+
+        #[test]
+        fn new_game_fetch_fills_placeholder_from_toggle() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let request = manager.prepare_guess("1").expect("new game");
+            assert!(matches!(request.reason, FetchReason::NewGame));
+
+            // 获取期间另一位成员切换了猜测模式
+            manager.toggle_direct_guess_mode("1");
+            let toggled = manager.get_direct_guess_status("1");
+
+            manager.commit_guess(
+                "1",
+                "a",
+                "A",
+                "企业".into(),
+                Some(fetched(request, &["企业"])),
+            );
+            let state = &manager.states["1"];
+            assert_eq!(state.words_rank_list.len(), 2);
+            assert_eq!(state.guesses[0].rank, Some(2));
+            assert_eq!(state.round, 0);
+            assert_eq!(state.direct_guess_enabled, toggled);
+        }
+
+        #[test]
+        fn progress_of_finished_round_stops_at_the_win() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let request = manager.prepare_guess("1").expect("new game");
+            let target = request.word_to_fetch.clone();
+            manager.commit_guess(
+                "1",
+                "a",
+                "A",
+                "企业".into(),
+                Some(fetched(request, &["企业"])),
+            );
+            manager.commit_guess("1", "b", "B", target, None);
+
+            let state = manager.states.get_mut("1").unwrap();
+            state.last_start_time -= chrono::Duration::hours(1);
+            let ended_at = state.last_start_time + chrono::Duration::minutes(5);
+            manager.round_records.last_mut().unwrap().ended_at = ended_at;
+
+            let progress = manager.get_progress("1");
+            assert!(progress.contains("本局已结束"), "{progress}");
+            assert!(
+                progress.contains(&format!(
+                    "用时：{}",
+                    format_duration(chrono::Duration::minutes(5))
+                )),
+                "{progress}"
+            );
+            assert!(progress.contains("最接近：#2（A）"), "{progress}");
+        }
+
+        #[test]
+        fn season_names_need_four_digit_years() {
+            for period in [
+                SeasonPeriod::Month,
+                SeasonPeriod::Quarter,
+                SeasonPeriod::Year,
+            ] {
+                let day = NaiveDate::from_ymd_opt(2026, 11, 5).unwrap();
+                let season = Season::containing(period, day);
+                assert_eq!(Season::parse(period, &season.name()), Some(season));
+            }
+            assert_eq!(Season::parse(SeasonPeriod::Year, "2"), None);
+            assert_eq!(Season::parse(SeasonPeriod::Quarter, "26Q1"), None);
+            assert_eq!(Season::parse(SeasonPeriod::Quarter, "2026Q5"), None);
+            assert_eq!(Season::parse(SeasonPeriod::Month, "2026-13"), None);
+        }
+
+        #[test]
+        fn guess_without_rank_list_is_not_recorded() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            manager.toggle_direct_guess_mode("1");
+
+            let reply = manager.commit_guess("1", "a", "A", "企业".into(), None);
+            assert_eq!(reply, "词语排名暂时获取不到，请稍后再试");
+            assert!(manager.states["1"].guesses.is_empty());
+        }
+
+        #[test]
+        fn assist_ties_go_to_the_earlier_assist_and_pages_show_own_rank() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let start = Utc::now() - chrono::Duration::hours(1);
+            for n in 0..12 {
+                manager.assist_records.push(AssistRecord {
+                    user_id: format!("u{n}"),
+                    username: format!("U{n}"),
+                    channel_id: "1".into(),
+                    timestamp: start + chrono::Duration::minutes(n),
+                    best_rank: 2,
+                    points: 10,
+                });
+            }
+            let query = |page| LeaderboardQuery {
+                sort: LeaderboardSort::Wins,
+                window: TimeWindow::All,
+                page,
+            };
+
+            let board = manager.get_assist_leaderboard("1", &query(1), "u11");
+            let lines: Vec<&str> = board.lines().collect();
+            assert_eq!(lines[0], "1. U0 1（10 分）");
+            assert_eq!(lines[9], "10. U9 1（10 分）");
+            assert_eq!(lines[10], "第 1/2 页");
+            assert_eq!(lines[11], "你的排名：12. U11 1（10 分）");
+
+            let board = manager.get_assist_leaderboard("1", &query(2), "u11");
+            assert_eq!(board, "11. U10 1（10 分）\n12. U11 1（10 分）\n第 2/2 页");
+            assert_eq!(
+                manager.get_assist_leaderboard("2", &query(1), "u11"),
+                "当前还没有人助攻过哦！"
+            );
+        }
+
+        #[test]
+        fn season_stays_open_until_every_channel_leaves_it() {
+            use crate::p_config::{ChannelScheduleConfig, TimeZoneSpec};
+            use chrono_tz::Tz;
+
+            let mut schedule = ScheduleConfig::default();
+            let now = "2026-10-31T18:00:00Z".parse::<DateTime<Utc>>().unwrap();
+            let october = Season::containing(
+                SeasonPeriod::Month,
+                NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            );
+            // 上海已是 11 月 1 日
+            assert_eq!(
+                open_season(&schedule, SeasonPeriod::Month, now),
+                october.next()
+            );
+
+            // 洛杉矶的频道还在 10 月 31 日，10 月不能存档
+            schedule.channels.insert(
+                "1".into(),
+                ChannelScheduleConfig {
+                    timezone: Some(TimeZoneSpec::Named(Tz::America__Los_Angeles)),
+                    reset_time: None,
+                },
+            );
+            assert_eq!(open_season(&schedule, SeasonPeriod::Month, now), october);
+            let later = now + chrono::Duration::hours(14);
+            assert_eq!(
+                open_season(&schedule, SeasonPeriod::Month, later),
+                october.next()
+            );
+        }
+
+        #[test]
+        fn season_archive_lookup_accepts_lowercase_and_unpadded_names() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let archive = |season: Season| SeasonArchive {
+                season,
+                channel_id: None,
+                sort: LeaderboardSort::Points,
+                standings: Vec::new(),
+                archived_at: Utc::now(),
+            };
+            let day = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+            manager.season_archives.extend([
+                archive(Season::containing(SeasonPeriod::Quarter, day)),
+                archive(Season::containing(SeasonPeriod::Month, day)),
+            ]);
+
+            for name in ["2026Q1", "2026q1"] {
+                let reply = manager.get_season_archive(None, Some(name));
+                assert!(reply.starts_with("赛季 2026Q1"), "{reply}");
+            }
+            for name in ["2026-01", "2026-1"] {
+                let reply = manager.get_season_archive(None, Some(name));
+                assert!(reply.starts_with("赛季 2026-01"), "{reply}");
+            }
+            let reply = manager.get_season_archive(None, Some("2026Q2"));
+            assert_eq!(reply, "没有找到赛季 2026Q2 的存档");
+        }
+
+        fn pending(word: &str, puzzle_number: Option<u32>) -> PendingRound {
+            PendingRound {
+                target_word: word.into(),
+                words_rank_list: vec![word.into(), "企业".into()],
+                fetched_at: Utc::now(),
+                puzzle_number,
+            }
+        }
+
+        #[test]
+        fn prefetched_round_starts_on_the_next_day() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let list = started_round(&mut manager);
+            manager.commit_guess("1", "b", "B", list[0].clone(), None);
+            assert!(manager.is_finished("1"));
+
+            let request = manager.prepare_prefetch("1").expect("prefetch");
+            assert!(matches!(request.reason, FetchReason::Prefetch));
+            let (data, pending_list) = fetched_from_memory(request);
+            manager.commit_prefetch("1", data);
+            assert!(manager.prepare_prefetch("1").is_none());
+
+            manager.states.get_mut("1").unwrap().last_start_time -= chrono::Duration::days(1);
+            // 换日后直接使用预取的一轮，不需要再获取
+            assert!(manager.prepare_guess("1").is_none());
+            manager.commit_guess("1", "a", "A", pending_list[1].clone(), None);
+            let state = &manager.states["1"];
+            assert_eq!(state.round, 1);
+            assert_eq!(state.target_word, pending_list[0]);
+            assert_eq!(state.words_rank_list, pending_list);
+            assert_eq!(state.guesses.len(), 1);
+            assert!(!state.is_finished);
+            assert!(state.pending_round.is_none());
+        }
+
+        #[test]
+        fn later_daily_puzzle_is_kept_through_an_extra_round() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let list = started_round(&mut manager);
+            manager.commit_guess("1", "b", "B", list[0].clone(), None);
+            let state = manager.states.get_mut("1").unwrap();
+            state.puzzle_number = Some(7);
+            state.pending_round = Some(pending("东西", Some(8)));
+
+            // 今天还是第 7 题，预取的第 8 题留到明天
+            state.last_start_time -= chrono::Duration::days(1);
+            state.roll_over_to(Some(7));
+            assert!(state.is_finished);
+            assert_eq!(
+                state.pending_round.as_ref().and_then(|p| p.puzzle_number),
+                Some(8)
+            );
+
+            // 加赛局不占用题号，也不会用掉预取的题
+            let request = FetchRequest {
+                word_to_fetch: "公司".into(),
+                reason: FetchReason::NewRound,
+                round: state.round,
+                puzzle_number: None,
+            };
+            let reply = manager.commit_new_round("1", Some(fetched(request, &["企业"])));
+            assert_eq!(reply, "新的一局开始了！");
+            let state = &manager.states["1"];
+            assert_eq!((state.round, state.puzzle_number), (1, None));
+            assert_eq!(
+                state.pending_round.as_ref().and_then(|p| p.puzzle_number),
+                Some(8)
+            );
+
+            manager.commit_guess("1", "a", "A", "公司".into(), None);
+            let state = manager.states.get_mut("1").unwrap();
+            assert!(state.is_finished);
+            state.last_start_time -= chrono::Duration::days(1);
+            state.roll_over_to(Some(8));
+            assert_eq!(state.round, 2);
+            assert_eq!(state.target_word, "东西");
+            assert_eq!(state.puzzle_number, Some(8));
+            assert!(!state.is_finished);
+            assert!(state.pending_round.is_none());
+        }
+
+        #[test]
+        fn stale_daily_prefetch_is_replaced() {
+            p_config::init_default();
+            let mut manager = CiYiGameManager::default();
+            let list = started_round(&mut manager);
+            manager.commit_guess("1", "b", "B", list[0].clone(), None);
+            let state = manager.states.get_mut("1").unwrap();
+            state.puzzle_number = Some(7);
+            state.pending_round = Some(pending("东西", Some(8)));
+            let round = state.round;
+
+            let prefetch = |word: &str, puzzle_number| FetchRequest {
+                word_to_fetch: word.into(),
+                reason: FetchReason::Prefetch,
+                round,
+                puzzle_number: Some(puzzle_number),
+            };
+            // 已经到了第 9 题，第 8 题的预取被新的预取替换
+            manager.commit_prefetch("1", fetched(prefetch("公司", 9), &["企业"]));
+            let pending_round = manager.states["1"].pending_round.as_ref().unwrap();
+            assert_eq!(pending_round.target_word, "公司");
+            assert_eq!(pending_round.puzzle_number, Some(9));
+
+            // 迟到的旧预取不会覆盖更新的一轮
+            manager.commit_prefetch("1", fetched(prefetch("东西", 8), &["企业"]));
+            let pending_round = manager.states["1"].pending_round.as_ref().unwrap();
+            assert_eq!(pending_round.target_word, "公司");
+
+            // 换日时预取的题已经过时，丢弃后等待重新获取
+            let state = manager.states.get_mut("1").unwrap();
+            state.last_start_time -= chrono::Duration::days(1);
+            state.roll_over_to(Some(10));
+            assert!(state.is_finished);
+            assert!(state.pending_round.is_none());
+            assert_eq!(state.round, round);
+        }
+
+        #[test]
+        fn rejecting_a_daily_word_keeps_other_puzzles() {
+            p_config::init_default();
+            let puzzles = 1..=(DAILY_ORDER.len() as u32 + 3);
+            let before: Vec<_> = puzzles
+                .clone()
+                .map(|n| daily_word(n, &HashSet::new()).unwrap())
+                .collect();
+
+            let rejected = HashSet::from([before[4].clone()]);
+            for (n, word) in puzzles.zip(&before) {
+                let after = daily_word(n, &rejected).unwrap();
+                if *word == before[4] {
+                    assert_ne!(after, *word);
+                    assert!(!rejected.contains(&after));
+                } else {
+                    assert_eq!(after, *word, "puzzle {n}");
+                }
+            }
+        }
+    }
+}
+
+mod p_actor {
+    use std::any::Any;
+    use std::fmt;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use kovi::log;
+    use tokio::sync::{mpsc, oneshot};
+
+    use crate::ciyi_game::CiYiGameManager;
+
+    type Job = Box<dyn FnOnce(&mut CiYiGameManager) + Send>;
+
+    /// 游戏任务已退出或操作中途崩溃。
+    #[derive(Debug)]
+    pub struct GameUnavailable;
+
+    impl fmt::Display for GameUnavailable {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "词意游戏暂时不可用，请稍后再试")
+        }
+    }
+
+    impl std::error::Error for GameUnavailable {}
+
+    /// `CiYiGameManager` 由独立任务持有，其余代码通过消息在其中执行操作。
+    /// 单个操作崩溃只会让该次调用失败，不会影响其他群组。
+    #[derive(Clone)]
+    pub struct GameHandle {
+        tx: mpsc::UnboundedSender<Job>,
+    }
+
+    impl GameHandle {
+        pub fn spawn(mut manager: CiYiGameManager) -> Self {
+            let (tx, mut rx) = mpsc::unbounded_channel::<Job>();
+            tokio::spawn(async move {
+                while let Some(job) = rx.recv().await {
+                    if let Err(payload) =
+                        panic::catch_unwind(AssertUnwindSafe(|| job(&mut manager)))
+                    {
+                        log::error!("Ciyi game operation panicked: {}", panic_message(&payload));
+                    }
+                }
+            });
+            Self { tx }
+        }
+
+        pub async fn call<R, F>(&self, f: F) -> Result<R, GameUnavailable>
+        where
+            F: FnOnce(&mut CiYiGameManager) -> R + Send + 'static,
+            R: Send + 'static,
+        {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            let job: Job = Box::new(move |manager| {
+                let _ = reply_tx.send(f(manager));
+            });
+            self.tx.send(job).map_err(|_| GameUnavailable)?;
+            reply_rx.await.map_err(|_| GameUnavailable)
+        }
+
+        /// 与 [`GameHandle::call`] 相同，但会将状态标记为待保存。
+        pub async fn update<R, F>(&self, f: F) -> Result<R, GameUnavailable>
+        where
+            F: FnOnce(&mut CiYiGameManager) -> R + Send + 'static,
+            R: Send + 'static,
+        {
+            self.call(move |manager| {
+                let result = f(manager);
+                manager.mark_dirty();
+                result
+            })
+            .await
+        }
+    }
+
+    fn panic_message(payload: &Box<dyn Any + Send>) -> &str {
+        payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic")
+    }
+}
+
+mod p_command {
+    use kovi::log;
+    use kovi::toml;
+    use kovi::utils::load_toml_data;
+    use serde::{Deserialize, Serialize};
+    use std::error::Error;
+    use std::path::PathBuf;
+    use std::sync::OnceLock;
+
+    pub static COMMAND: OnceLock<CommandConfig> = OnceLock::new();
+    pub fn commands() -> &'static CommandConfig {
+        COMMAND.get().expect("Commands not initialized")
+    }
+
+    pub const DEFAULT_COMMANDS_STR: &str = r#"
+# 定义插件的指令。每个 [[command]] 块代表一种功能及其关联的触发词。
+# function: 功能的内部描述，用于代码逻辑判断。
+# commands: 用户可以输入的指令列表。
+
+[[command]]
+function = "查看插件指令列表"
+commands = ["词意帮助", "词意指令", "词意指令列表", "词意帮助列表"]
+
+[[command]]
+function = "查看词意游戏规则"
+commands = ["词意玩法", "词意规则"]
 
 [[command]]
 function = "猜测两字词语"
@@ -424,6 +2788,38 @@ commands = ["词意全榜"]
 [[command]]
 function = "切换猜测模式"
 commands = ["切换猜测模式"]
+
+[[command]]
+function = "放弃本轮"
+commands = ["词意放弃"]
+
+[[command]]
+function = "开始新的一局"
+commands = ["词意新局"]
+
+[[command]]
+function = "获取提示"
+commands = ["词意提示"]
+
+[[command]]
+function = "查看本局进度"
+commands = ["词意进度"]
+
+[[command]]
+function = "回放本局"
+commands = ["词意回放"]
+
+[[command]]
+function = "查看当前频道的助攻榜"
+commands = ["词意助攻榜"]
+
+[[command]]
+function = "查看我的数据"
+commands = ["词意我的"]
+
+[[command]]
+function = "查看往届赛季"
+commands = ["词意赛季"]
 "#;
 
     #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -453,11 +2849,33 @@ commands = ["切换猜测模式"]
             let mut config: CommandConfig =
                 load_toml_data(default_config, config_file_path.clone())?;
 
+            // 已有的 command.toml 不会被默认值覆盖，新版本增加的指令需要补上
+            let added = config.merge_defaults(toml::from_str(DEFAULT_COMMANDS_STR)?);
+            if !added.is_empty() {
+                std::fs::write(&config_file_path, toml::to_string_pretty(&config)?)?;
+                log::info!(
+                    "Added new ciyi commands to command.toml: {}",
+                    added.join(", ")
+                );
+            }
+
             config.config_file_path = config_file_path;
 
             Ok(config)
         }
 
+        /// 补上默认指令中本配置还没有的功能，返回补上的功能名。
+        fn merge_defaults(&mut self, defaults: CommandConfig) -> Vec<String> {
+            let mut added = Vec::new();
+            for entry in defaults.command {
+                if self.command.iter().all(|e| e.function != entry.function) {
+                    added.push(entry.function.clone());
+                    self.command.push(entry);
+                }
+            }
+            added
+        }
+
         pub fn get_function_by_command(&self, cmd_str: &str) -> Option<&String> {
             for entry in &self.command {
                 if entry.commands.iter().any(|cmd| cmd == cmd_str) {
@@ -467,12 +2885,48 @@ commands = ["切换猜测模式"]
             None
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn merge_defaults_adds_only_missing_functions() {
+            let mut config: CommandConfig = toml::from_str(
+                r#"
+[[command]]
+function = "猜测两字词语"
+commands = ["猜"]
+"#,
+            )
+            .unwrap();
+            let defaults: CommandConfig = toml::from_str(DEFAULT_COMMANDS_STR).unwrap();
+            let total = defaults.command.len();
+
+            let added = config.merge_defaults(defaults);
+            assert_eq!(added.len(), total - 1);
+            assert!(added.iter().any(|f| f == "放弃本轮"));
+            assert_eq!(config.command.len(), total);
+            // 用户改过的指令保持不变
+            assert_eq!(
+                config.get_function_by_command("猜").map(String::as_str),
+                Some("猜测两字词语")
+            );
+            assert_eq!(config.get_function_by_command("词意猜测"), None);
+
+            let defaults: CommandConfig = toml::from_str(DEFAULT_COMMANDS_STR).unwrap();
+            assert!(config.merge_defaults(defaults).is_empty());
+        }
+    }
 }
 
 mod p_config {
+    use chrono_tz::Tz;
+    use kovi::chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
     use kovi::toml;
     use kovi::utils::load_toml_data;
     use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
     use std::error::Error;
     use std::path::PathBuf;
     use std::sync::OnceLock;
@@ -483,116 +2937,3278 @@ mod p_config {
         CONFIG.get().expect("Config not initialized")
     }
 
+    /// 测试中使用默认配置。
+    #[cfg(test)]
+    pub fn init_default() {
+        let _ = CONFIG.set(toml::from_str(DEFAULT_CONFIG_STR).expect("default config"));
+    }
+
     pub const DEFAULT_CONFIG_STR: &str = r#"
 # 群组过滤
 [channel]
 
-# 白名单群组，如果非空，则只在这些群组响应。
-white = []
-# 黑名单群组，在这些群组中插件将不响应。
-black = ["123456789"]
+# 白名单群组，如果非空，则只在这些群组响应。
+white = []
+# 黑名单群组，在这些群组中插件将不响应。
+black = ["123456789"]
+
+# 插件配置
+[plugin]
+
+# 只有 @ Bot 时才回复
+only_at = false
+
+# 指令前缀 示例：["!", "。"]
+prefixes = []
+
+# Bot 响应时 @ 用户
+at_user = false
+
+# Bot 响应时引用用户消息
+quote_user = true
+
+# 是否开启直接猜测模式（不需要指令，直接发送两字词语即可猜测）
+direct_guess = false
+
+# 提示中显示几个历史记录
+history_display = 10
+
+# 排行榜每页显示几个人
+rank_display = 10
+
+# 额外的目标词语，需在词库中，例如 ["哀悼", "缅怀"]
+extra_question_words = []
+
+# 插件管理员 QQ 号，与群主、群管理员拥有相同权限
+admins = []
+
+# 词语排名来源
+[rank_list]
+
+# 来源类型：http（远程服务）、local（本地目录）、memory（启动时将本地目录载入内存）、
+# embedding（由本地词向量计算相似度）
+provider = "http"
+
+# http 来源的地址模板，{word} 会被替换为目标词语
+url = "https://ci-ying.oss-cn-zhangjiakou.aliyuncs.com/v1/ci-yi-list/{word}.txt"
+
+# http 来源的连接超时与读取超时（秒）
+connect_timeout_secs = 5
+read_timeout_secs = 15
+
+# http 来源失败后的重试次数，首次重试前等待 retry_backoff_ms 毫秒，之后每次翻倍
+max_retries = 3
+retry_backoff_ms = 500
+
+# http 来源一次获取（含全部重试与等待）的总时限（秒）
+fetch_timeout_secs = 30
+
+# http 来源连续失败多少次后暂停访问，以及暂停多少秒，0 表示不暂停
+# 暂停结束后先只放行一个请求试探，成功后才恢复访问
+breaker_threshold = 5
+breaker_cooldown_secs = 300
+
+# local / memory 来源的目录，目录下存放 {word}.txt，相对路径基于插件数据目录
+local_dir = "rank_lists"
+
+# embedding 来源的词向量文件（word2vec / fastText 格式），相对路径基于插件数据目录
+embedding_path = "embeddings.txt"
+
+# 词向量文件格式：text（文本格式）或 binary（word2vec 二进制格式）
+embedding_format = "text"
+
+# 排名列表本地缓存，目录：rank_cache/
+# 读出的缓存同样经过校验，不合格的会被删除并重新获取
+[rank_cache]
+
+# 是否启用缓存
+enabled = true
+
+# 是否以 gzip 压缩缓存文件（存为 {word}.txt.gz）
+compress = false
+
+# 缓存有效期（小时），0 表示永不过期；过期的缓存仍会在获取失败时作为备用
+ttl_hours = 0
+
+# 最多缓存多少个词语，超出时删除最久未更新的，0 表示不限
+max_entries = 0
+
+# 仅离线模式：只使用缓存，不访问排名来源
+offline_only = false
+
+# 预取下一轮的目标词语与排名列表，换日时无需等待获取
+[prefetch]
+
+# 是否启用（猜对后立即预取）
+enabled = true
+
+# 后台为尚无预取的群组获取下一轮的间隔（分钟），失败时下次重试，0 表示不定时预取
+interval_minutes = 30
+
+# 数据存储
+[storage]
+
+# 存储方式：json（ciyi_game_data.json）或 sqlite（ciyi_game.db，需启用 sqlite 特性）
+# 切换到 sqlite 时会自动导入已有的 JSON 数据
+backend = "json"
+
+# 状态变化后最多等待多少秒保存一次，0 表示每次变化立即保存
+save_interval_secs = 5
+
+# json 存储在 backups/ 下保留的备份数量，以及两次备份的最短间隔（分钟）
+# 数据文件损坏时自动从最新的有效备份恢复
+backup_count = 5
+backup_interval_minutes = 60
+
+# 每日周期
+[schedule]
+
+# 时区：IANA 名称（如 "Asia/Shanghai"）或固定偏移（如 "+08:00"）
+timezone = "Asia/Shanghai"
+
+# 每天几点换题（当地时间，HH:MM）
+reset_time = "00:00"
+
+# 到换题时间仍无人猜出时：continue（继续猜同一个词语）、reveal（公布答案，之后换新词语）
+unsolved = "continue"
+
+# 每天最多玩几局，0 为不限；多于 1 局时，本局结束后发送「词意新局」开始下一局
+rounds_per_day = 1
+
+# 单独设置某个群组，未写的项沿用上面的值
+# [schedule.channels."123456789"]
+# timezone = "America/New_York"
+# reset_time = "06:00"
+
+# 每日一题
+[daily]
+
+# 是否所有群组每天猜同一个词语（按上面的全局时区和换题时间计算日期）
+global = false
+
+# 第 1 题的日期
+epoch = "2025-01-01"
+
+# 出题顺序的随机种子，修改后题目顺序会改变
+seed = 0
+
+# 放弃本轮
+[give_up]
+
+# 谁可以放弃：anyone（任何人）、admin（仅管理员）、vote（投票，管理员可直接放弃）
+mode = "vote"
+
+# 投票模式下需要的票数
+votes_required = 3
+
+# 公布答案时显示排名前几的词语
+reveal_top = 10
+
+# 提示（词意提示）
+[hint]
+
+# 每局最多使用几次提示，0 表示不能使用
+max_per_round = 4
+
+# 每次提示前本局需要再猜几次，例如为 5 时第 1 次提示需猜 5 次，第 2 次需猜 10 次
+guesses_per_hint = 5
+
+# 开局多少分钟后才能使用提示，0 表示不限
+unlock_minutes = 0
+
+# 依次给出排名列表中这些名次的词语，之后给出目标词语的第一个字
+ranks = [100, 50, 10]
+
+# 计分
+# 得分 = base - per_guess_penalty × 本局猜测次数
+#      + own_guess_bonus × 猜对者自己的猜测次数 / 本局猜测次数
+#      - per_hint_penalty × 本局提示次数
+#      + best_rank_bonus / 猜对者猜过的最好名次（不含答案）
+# 结果不低于 min_points
+[score]
+
+# 排行榜默认排序：wins（猜对次数）或 points（积分），也可在指令后加「胜场」或「积分」
+# 指令后还可加统计范围：今日、本周、本月、赛季 [名称] 或 2026-01-01~2026-01-31，
+# 以及页码，如「词意榜 本周 2」；「词意助攻榜」的参数相同
+sort_by = "wins"
+
+base = 100.0
+per_guess_penalty = 0.5
+own_guess_bonus = 30.0
+per_hint_penalty = 15.0
+best_rank_bonus = 20.0
+min_points = 10.0
+
+# 助攻：有人猜对时，本局猜到排名前 top_k 的其他成员各记一次助攻
+[assist]
+
+# 是否启用
+enabled = false
+
+# 猜到前几名算助攻（不含答案本身）
+top_k = 10
+
+# 每次助攻的积分，计入助攻榜
+points = 20
+
+# 赛季：排行榜可按赛季统计，如「词意榜 赛季」或「词意榜 赛季 2026Q1」
+[season]
+
+# 是否启用赛季：启用后排行榜默认只统计本赛季（加「全部」查看历史总榜），
+# 赛季结束时最终排名存档，可用「词意赛季」查看
+enabled = false
+
+# 赛季周期：month（自然月，名称如 2026-03）、quarter（季度，如 2026Q1）或 year（如 2026）
+# 按 [schedule] 的时区与换题时刻划分游戏日
+period = "quarter"
+"#;
+
+    /// [channel]
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ChannelConfig {
+        pub white: Vec<String>,
+        pub black: Vec<String>,
+    }
+
+    /// [plugin]
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct PluginConfig {
+        pub only_at: bool,
+        pub prefixes: Vec<String>,
+        pub at_user: bool,
+        pub quote_user: bool,
+        pub direct_guess: bool,
+        pub history_display: usize,
+        pub rank_display: usize,
+        #[serde(default)]
+        pub extra_question_words: Vec<String>,
+        #[serde(default)]
+        pub admins: Vec<String>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum GiveUpMode {
+        Anyone,
+        Admin,
+        Vote,
+    }
+
+    /// [give_up]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct GiveUpConfig {
+        pub mode: GiveUpMode,
+        pub votes_required: usize,
+        pub reveal_top: usize,
+    }
+
+    impl Default for GiveUpConfig {
+        fn default() -> Self {
+            Self {
+                mode: GiveUpMode::Vote,
+                votes_required: 3,
+                reveal_top: 10,
+            }
+        }
+    }
+
+    /// [hint]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct HintConfig {
+        pub max_per_round: usize,
+        pub guesses_per_hint: usize,
+        pub unlock_minutes: u64,
+        pub ranks: Vec<usize>,
+    }
+
+    impl Default for HintConfig {
+        fn default() -> Self {
+            Self {
+                max_per_round: 4,
+                guesses_per_hint: 5,
+                unlock_minutes: 0,
+                ranks: vec![100, 50, 10],
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum LeaderboardSort {
+        Wins,
+        Points,
+    }
+
+    /// [score]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct ScoreConfig {
+        pub sort_by: LeaderboardSort,
+        pub base: f64,
+        pub per_guess_penalty: f64,
+        pub own_guess_bonus: f64,
+        pub per_hint_penalty: f64,
+        pub best_rank_bonus: f64,
+        pub min_points: f64,
+    }
+
+    impl Default for ScoreConfig {
+        fn default() -> Self {
+            Self {
+                sort_by: LeaderboardSort::Wins,
+                base: 100.0,
+                per_guess_penalty: 0.5,
+                own_guess_bonus: 30.0,
+                per_hint_penalty: 15.0,
+                best_rank_bonus: 20.0,
+                min_points: 10.0,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum SeasonPeriod {
+        Month,
+        Quarter,
+        Year,
+    }
+
+    /// [season]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct SeasonConfig {
+        pub enabled: bool,
+        pub period: SeasonPeriod,
+    }
+
+    impl Default for SeasonConfig {
+        fn default() -> Self {
+            Self {
+                enabled: false,
+                period: SeasonPeriod::Quarter,
+            }
+        }
+    }
+
+    /// [assist]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct AssistConfig {
+        pub enabled: bool,
+        pub top_k: usize,
+        pub points: u32,
+    }
+
+    impl Default for AssistConfig {
+        fn default() -> Self {
+            Self {
+                enabled: false,
+                top_k: 10,
+                points: 20,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum RankListSource {
+        Http,
+        Local,
+        Memory,
+        Embedding,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum EmbeddingFormat {
+        Text,
+        Binary,
+    }
+
+    /// [rank_list]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct RankListConfig {
+        pub provider: RankListSource,
+        pub url: String,
+        pub connect_timeout_secs: u64,
+        pub read_timeout_secs: u64,
+        pub max_retries: u32,
+        pub retry_backoff_ms: u64,
+        pub fetch_timeout_secs: u64,
+        pub breaker_threshold: u32,
+        pub breaker_cooldown_secs: u64,
+        pub local_dir: PathBuf,
+        pub embedding_path: PathBuf,
+        pub embedding_format: EmbeddingFormat,
+    }
+
+    impl Default for RankListConfig {
+        fn default() -> Self {
+            Self {
+                provider: RankListSource::Http,
+                url: "https://ci-ying.oss-cn-zhangjiakou.aliyuncs.com/v1/ci-yi-list/{word}.txt"
+                    .to_string(),
+                connect_timeout_secs: 5,
+                read_timeout_secs: 15,
+                max_retries: 3,
+                retry_backoff_ms: 500,
+                fetch_timeout_secs: 30,
+                breaker_threshold: 5,
+                breaker_cooldown_secs: 300,
+                local_dir: PathBuf::from("rank_lists"),
+                embedding_path: PathBuf::from("embeddings.txt"),
+                embedding_format: EmbeddingFormat::Text,
+            }
+        }
+    }
+
+    /// [rank_cache]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct RankCacheConfig {
+        pub enabled: bool,
+        pub compress: bool,
+        pub ttl_hours: u64,
+        pub max_entries: usize,
+        pub offline_only: bool,
+    }
+
+    impl Default for RankCacheConfig {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                compress: false,
+                ttl_hours: 0,
+                max_entries: 0,
+                offline_only: false,
+            }
+        }
+    }
+
+    /// [prefetch]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct PrefetchConfig {
+        pub enabled: bool,
+        pub interval_minutes: u64,
+    }
+
+    impl Default for PrefetchConfig {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                interval_minutes: 30,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum StorageBackend {
+        Json,
+        Sqlite,
+    }
+
+    /// [storage]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct StorageConfig {
+        pub backend: StorageBackend,
+        pub save_interval_secs: u64,
+        pub backup_count: usize,
+        pub backup_interval_minutes: u64,
+    }
+
+    impl Default for StorageConfig {
+        fn default() -> Self {
+            Self {
+                backend: StorageBackend::Json,
+                save_interval_secs: 5,
+                backup_count: 5,
+                backup_interval_minutes: 60,
+            }
+        }
+    }
+
+    /// 时区：IANA 名称或固定偏移。
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(try_from = "String", into = "String")]
+    pub enum TimeZoneSpec {
+        Named(Tz),
+        Fixed(FixedOffset),
+    }
+
+    impl TryFrom<String> for TimeZoneSpec {
+        type Error = String;
+
+        fn try_from(value: String) -> Result<Self, Self::Error> {
+            if let Ok(tz) = value.parse::<Tz>() {
+                return Ok(Self::Named(tz));
+            }
+            value
+                .parse::<FixedOffset>()
+                .map(Self::Fixed)
+                .map_err(|_| format!("无效的时区：{value}"))
+        }
+    }
+
+    impl From<TimeZoneSpec> for String {
+        fn from(value: TimeZoneSpec) -> Self {
+            match value {
+                TimeZoneSpec::Named(tz) => tz.name().to_string(),
+                TimeZoneSpec::Fixed(offset) => offset.to_string(),
+            }
+        }
+    }
+
+    impl TimeZoneSpec {
+        pub fn to_local(self, instant: DateTime<Utc>) -> NaiveDateTime {
+            match self {
+                Self::Named(tz) => instant.with_timezone(&tz).naive_local(),
+                Self::Fixed(offset) => instant.with_timezone(&offset).naive_local(),
+            }
+        }
+    }
+
+    /// 当地时间的换题时刻，格式 HH:MM。
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(try_from = "String", into = "String")]
+    pub struct ResetTime(pub NaiveTime);
+
+    impl TryFrom<String> for ResetTime {
+        type Error = String;
+
+        fn try_from(value: String) -> Result<Self, Self::Error> {
+            NaiveTime::parse_from_str(&value, "%H:%M")
+                .map(Self)
+                .map_err(|_| format!("无效的换题时刻：{value}，应为 HH:MM"))
+        }
+    }
+
+    impl From<ResetTime> for String {
+        fn from(value: ResetTime) -> Self {
+            value.0.format("%H:%M").to_string()
+        }
+    }
+
+    /// 某个频道的每日周期。
+    #[derive(Debug, Clone, Copy)]
+    pub struct DayCycle {
+        pub timezone: TimeZoneSpec,
+        pub reset_time: NaiveTime,
+    }
+
+    impl DayCycle {
+        /// 某一时刻所属的游戏日：当地时间减去换题时刻后的日期。
+        /// 在当地时间上做减法，夏令时跳变的那天也只会换一次日。
+        pub fn game_day(&self, instant: DateTime<Utc>) -> NaiveDate {
+            let local = self.timezone.to_local(instant);
+            (local - (self.reset_time - NaiveTime::MIN)).date()
+        }
+
+        /// 夏令时结束时当地时间会回拨，游戏日可能短暂后退，只有前进才算新的一天。
+        pub fn is_new_day(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+            self.game_day(now) > self.game_day(since)
+        }
+    }
+
+    /// [schedule.channels."群号"]
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct ChannelScheduleConfig {
+        pub timezone: Option<TimeZoneSpec>,
+        pub reset_time: Option<ResetTime>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum UnsolvedPolicy {
+        Continue,
+        Reveal,
+    }
+
+    /// [schedule]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct ScheduleConfig {
+        pub timezone: TimeZoneSpec,
+        pub reset_time: ResetTime,
+        pub unsolved: UnsolvedPolicy,
+        pub rounds_per_day: usize,
+        pub channels: HashMap<String, ChannelScheduleConfig>,
+    }
+
+    impl Default for ScheduleConfig {
+        fn default() -> Self {
+            Self {
+                timezone: TimeZoneSpec::Named(Tz::Asia__Shanghai),
+                reset_time: ResetTime(NaiveTime::MIN),
+                unsolved: UnsolvedPolicy::Continue,
+                rounds_per_day: 1,
+                channels: HashMap::new(),
+            }
+        }
+    }
+
+    impl ScheduleConfig {
+        pub fn default_cycle(&self) -> DayCycle {
+            DayCycle {
+                timezone: self.timezone,
+                reset_time: self.reset_time.0,
+            }
+        }
+
+        pub fn cycle(&self, channel_id: &str) -> DayCycle {
+            let default = self.default_cycle();
+            let Some(channel) = self.channels.get(channel_id) else {
+                return default;
+            };
+            DayCycle {
+                timezone: channel.timezone.unwrap_or(default.timezone),
+                reset_time: channel.reset_time.map_or(default.reset_time, |t| t.0),
+            }
+        }
+    }
+
+    /// [daily]
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct DailyConfig {
+        pub global: bool,
+        pub epoch: NaiveDate,
+        pub seed: u64,
+    }
+
+    impl Default for DailyConfig {
+        fn default() -> Self {
+            Self {
+                global: false,
+                epoch: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap_or_default(),
+                seed: 0,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct Config {
+        pub channel: ChannelConfig,
+        pub plugin: PluginConfig,
+        #[serde(default)]
+        pub rank_list: RankListConfig,
+        #[serde(default)]
+        pub rank_cache: RankCacheConfig,
+        #[serde(default)]
+        pub prefetch: PrefetchConfig,
+        #[serde(default)]
+        pub storage: StorageConfig,
+        #[serde(default)]
+        pub schedule: ScheduleConfig,
+        #[serde(default)]
+        pub daily: DailyConfig,
+        #[serde(default)]
+        pub give_up: GiveUpConfig,
+        #[serde(default)]
+        pub hint: HintConfig,
+        #[serde(default)]
+        pub score: ScoreConfig,
+        #[serde(default)]
+        pub assist: AssistConfig,
+        #[serde(default)]
+        pub season: SeasonConfig,
+
+        #[serde(skip)]
+        config_file_path: PathBuf,
+    }
+
+    impl Config {
+        pub fn new(data_dir: PathBuf) -> Result<Self, Box<dyn Error>> {
+            if !data_dir.exists() {
+                std::fs::create_dir_all(&data_dir)?;
+            }
+
+            let config_file_path = data_dir.join("config.toml");
+
+            let default_config: Config = toml::from_str(DEFAULT_CONFIG_STR)?;
+
+            let mut config: Config = load_toml_data(default_config, config_file_path.clone())?;
+
+            config.config_file_path = config_file_path;
+
+            Ok(config)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use kovi::chrono::TimeZone;
+
+        fn new_york(reset_time: NaiveTime) -> DayCycle {
+            DayCycle {
+                timezone: TimeZoneSpec::Named(Tz::America__New_York),
+                reset_time,
+            }
+        }
+
+        fn hm(hour: u32, minute: u32) -> NaiveTime {
+            NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+        }
+
+        #[test]
+        fn spring_forward_skips_reset_time() {
+            // 2025-03-09 02:00 EST 跳到 03:00 EDT，02:30 在当地并不存在
+            let cycle = new_york(hm(2, 30));
+            let before = Utc.with_ymd_and_hms(2025, 3, 9, 6, 59, 0).unwrap(); // 01:59 EST
+            let after = Utc.with_ymd_and_hms(2025, 3, 9, 7, 1, 0).unwrap(); // 03:01 EDT
+            assert!(cycle.is_new_day(before, after));
+            assert_eq!(
+                cycle.game_day(after),
+                NaiveDate::from_ymd_opt(2025, 3, 9).unwrap()
+            );
+        }
+
+        #[test]
+        fn fall_back_repeated_hour_changes_day_once() {
+            // 2025-11-02 02:00 EDT 回拨到 01:00 EST，01:30 出现两次
+            let cycle = new_york(hm(1, 30));
+            let first = Utc.with_ymd_and_hms(2025, 11, 2, 5, 45, 0).unwrap(); // 01:45 EDT
+            let repeated = Utc.with_ymd_and_hms(2025, 11, 2, 6, 10, 0).unwrap(); // 01:10 EST
+            let second = Utc.with_ymd_and_hms(2025, 11, 2, 6, 40, 0).unwrap(); // 01:40 EST
+            assert!(!cycle.is_new_day(first, repeated));
+            assert!(!cycle.is_new_day(first, second));
+
+            let next = Utc.with_ymd_and_hms(2025, 11, 3, 6, 31, 0).unwrap(); // 01:31 EST
+            assert!(cycle.is_new_day(first, next));
+        }
+
+        #[test]
+        fn schedule_types_round_trip_through_toml() {
+            #[derive(Debug, PartialEq, Serialize, Deserialize)]
+            struct Schedule {
+                named: TimeZoneSpec,
+                fixed: TimeZoneSpec,
+                reset_time: ResetTime,
+            }
+
+            let text = r#"
+named = "America/New_York"
+fixed = "+08:00"
+reset_time = "02:30"
+"#;
+            let schedule: Schedule = toml::from_str(text).unwrap();
+            assert_eq!(schedule.named, TimeZoneSpec::Named(Tz::America__New_York));
+            assert!(matches!(schedule.fixed, TimeZoneSpec::Fixed(_)));
+            assert_eq!(schedule.reset_time, ResetTime(hm(2, 30)));
+
+            let back: Schedule = toml::from_str(&toml::to_string(&schedule).unwrap()).unwrap();
+            assert_eq!(back, schedule);
+
+            assert!(toml::from_str::<Schedule>(&text.replace("02:30", "25:00")).is_err());
+            assert!(toml::from_str::<Schedule>(&text.replace("+08:00", "Mars/Base")).is_err());
+        }
+    }
+}
+
+mod p_const {
+    use kovi::{log, serde_json};
+    use once_cell::sync::Lazy;
+    use std::collections::HashSet;
+
+    use crate::p_config;
+
+    const ALL_WORDS_JSON: &str = include_str!("../res/all_words.json");
+    const QUESTION_WORDS_JSON: &str = include_str!("../res/question_words.json");
+
+    pub static ALL_WORDS: Lazy<HashSet<String>> = Lazy::new(|| {
+        let words: Vec<String> =
+            serde_json::from_str(ALL_WORDS_JSON).expect("Failed to parse all_words.json");
+        words.into_iter().collect()
+    });
+
+    pub static QUESTION_WORDS: Lazy<Vec<String>> = Lazy::new(|| {
+        serde_json::from_str(QUESTION_WORDS_JSON).expect("Failed to parse question_words.json")
+    });
+
+    /// 内置题库加上配置中的 `extra_question_words`。
+    pub static TARGET_WORDS: Lazy<Vec<String>> = Lazy::new(|| {
+        let mut words = QUESTION_WORDS.clone();
+        let mut seen: HashSet<String> = words.iter().cloned().collect();
+        for word in &p_config::config().plugin.extra_question_words {
+            if !ALL_WORDS.contains(word) {
+                log::warn!("Extra ciyi question word {word} is not in the word list, skipped");
+                continue;
+            }
+            if seen.insert(word.clone()) {
+                words.push(word.clone());
+            }
+        }
+        words
+    });
+}
+
+mod p_rank {
+    use std::collections::{HashMap, HashSet};
+    use std::error::Error;
+    use std::fmt;
+    use std::fs::File;
+    use std::future::Future;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant, SystemTime};
+
+    use flate2::Compression;
+    use flate2::read::GzDecoder;
+    use flate2::write::GzEncoder;
+    use kovi::log;
+
+    use crate::p_config::{EmbeddingFormat, RankCacheConfig, RankListConfig, RankListSource};
+    use crate::p_const::ALL_WORDS;
+
+    pub type RankListResult = Result<Vec<String>, Box<dyn Error + Send + Sync>>;
+    pub type RankListFuture<'a> = Pin<Box<dyn Future<Output = RankListResult> + Send + 'a>>;
+    pub type DiscardFuture<'a> = Pin<Box<dyn Future<Output = bool> + Send + 'a>>;
+
+    /// 目标词语的相似度排名列表来源，列表按相似度从高到低排列。
+    pub trait RankListProvider: Send + Sync {
+        fn fetch<'a>(&'a self, word: &'a str) -> RankListFuture<'a>;
+
+        /// 丢弃保存下来的 `word` 的排名列表，返回是否确有丢弃。
+        /// 只有带缓存的来源需要实现。
+        fn discard<'a>(&'a self, _word: &'a str) -> DiscardFuture<'a> {
+            Box::pin(async { false })
+        }
+    }
+
+    pub fn parse_rank_list(text: &str) -> Vec<String> {
+        text.trim()
+            .split('\n')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// 连续失败达到阈值后在冷却期内直接拒绝请求。冷却结束后只放行一个试探请求，
+    /// 试探期间其余请求仍被拒绝；试探成功后恢复，失败则重新冷却。
+    struct CircuitBreaker {
+        threshold: u32,
+        cooldown: Duration,
+        state: Mutex<BreakerState>,
+    }
+
+    #[derive(Default)]
+    struct BreakerState {
+        consecutive_failures: u32,
+        open_until: Option<Instant>,
+        /// 冷却结束后已有请求在试探
+        trial_in_flight: bool,
+    }
+
+    impl CircuitBreaker {
+        fn new(threshold: u32, cooldown: Duration) -> Self {
+            Self {
+                threshold,
+                cooldown,
+                state: Mutex::new(BreakerState::default()),
+            }
+        }
+
+        /// 是否放行本次请求，冷却结束后由第一个调用者取得试探名额。
+        fn try_acquire(&self) -> Option<BreakerPermit<'_>> {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let trial = match state.open_until {
+                None => false,
+                Some(until) if Instant::now() < until => return None,
+                Some(_) if state.trial_in_flight => return None,
+                Some(_) => {
+                    state.trial_in_flight = true;
+                    true
+                }
+            };
+            Some(BreakerPermit {
+                breaker: self,
+                trial,
+            })
+        }
+
+        fn release(&self) {
+            self.state
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .trial_in_flight = false;
+        }
+
+        fn record_success(&self) {
+            *self.state.lock().unwrap_or_else(|e| e.into_inner()) = BreakerState::default();
+        }
+
+        fn record_failure(&self) {
+            if self.threshold == 0 {
+                return;
+            }
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.consecutive_failures += 1;
+            if state.consecutive_failures >= self.threshold {
+                if state.open_until.is_none_or(|until| Instant::now() >= until) {
+                    log::warn!(
+                        "Ciyi rank list service failed {} times in a row, pausing for {:?}",
+                        state.consecutive_failures,
+                        self.cooldown
+                    );
+                }
+                state.open_until = Some(Instant::now() + self.cooldown);
+                state.trial_in_flight = false;
+            }
+        }
+    }
+
+    /// [`CircuitBreaker::try_acquire`] 放行的请求。试探请求没有记下结果就被丢弃时
+    /// （请求以与服务是否可用无关的结果结束，或任务被取消），归还试探名额。
+    struct BreakerPermit<'a> {
+        breaker: &'a CircuitBreaker,
+        trial: bool,
+    }
+
+    impl BreakerPermit<'_> {
+        fn record_success(mut self) {
+            self.trial = false;
+            self.breaker.record_success();
+        }
+
+        fn record_failure(mut self) {
+            self.trial = false;
+            self.breaker.record_failure();
+        }
+    }
+
+    impl Drop for BreakerPermit<'_> {
+        fn drop(&mut self) {
+            if self.trial {
+                self.breaker.release();
+            }
+        }
+    }
+
+    /// 从远程服务获取，`url` 中的 `{word}` 会被替换为目标词语。
+    /// 超时与服务端错误按指数退避重试，连续失败过多时暂停访问。
+    pub struct HttpRankListProvider {
+        client: reqwest::Client,
+        url_template: String,
+        max_retries: u32,
+        retry_backoff: Duration,
+        fetch_timeout: Duration,
+        breaker: CircuitBreaker,
+    }
+
+    impl HttpRankListProvider {
+        pub fn new(config: &RankListConfig) -> Result<Self, reqwest::Error> {
+            let client = reqwest::Client::builder()
+                .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+                .read_timeout(Duration::from_secs(config.read_timeout_secs))
+                .build()?;
+            Ok(Self {
+                client,
+                url_template: config.url.clone(),
+                max_retries: config.max_retries,
+                retry_backoff: Duration::from_millis(config.retry_backoff_ms),
+                fetch_timeout: Duration::from_secs(config.fetch_timeout_secs),
+                breaker: CircuitBreaker::new(
+                    config.breaker_threshold,
+                    Duration::from_secs(config.breaker_cooldown_secs),
+                ),
+            })
+        }
+
+        async fn fetch_once(&self, url: &str) -> Result<Vec<String>, reqwest::Error> {
+            let response = self.client.get(url).send().await?;
+            let response = response.error_for_status()?;
+            let body_text = response.text().await?;
+            Ok(parse_rank_list(&body_text))
+        }
+
+        async fn fetch_with_retries(&self, word: &str) -> RankListResult {
+            let url = self.url_template.replace("{word}", word);
+            let mut attempt = 0;
+            loop {
+                let Some(permit) = self.breaker.try_acquire() else {
+                    return Err("排名服务连续失败，暂停访问中".into());
+                };
+                let e = match self.fetch_once(&url).await {
+                    Ok(list) => {
+                        permit.record_success();
+                        return Ok(list);
+                    }
+                    Err(e) => e,
+                };
+                if e.status() == Some(reqwest::StatusCode::NOT_FOUND) {
+                    return Err(RankListNotFound(word.to_string()).into());
+                }
+                if !is_transient(&e) {
+                    return Err(e.into());
+                }
+                permit.record_failure();
+                if attempt >= self.max_retries {
+                    return Err(e.into());
+                }
+                let delay = self.retry_backoff * 2u32.saturating_pow(attempt);
+                log::warn!(
+                    "Fetching ciyi rank list for {word} failed (attempt {}), retrying in {delay:?}: {e}",
+                    attempt + 1
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+
+    /// 4xx（如词语不存在）重试也没有用，其余错误视为暂时性故障。
+    fn is_transient(e: &reqwest::Error) -> bool {
+        e.status().is_none_or(|status| !status.is_client_error())
+    }
+
+    impl RankListProvider for HttpRankListProvider {
+        fn fetch<'a>(&'a self, word: &'a str) -> RankListFuture<'a> {
+            Box::pin(async move {
+                // 连接、读取超时加上退避等待，不设总时限时一次猜测可能卡住一分多钟
+                match tokio::time::timeout(self.fetch_timeout, self.fetch_with_retries(word)).await
+                {
+                    Ok(result) => result,
+                    Err(_) => {
+                        self.breaker.record_failure();
+                        Err(format!("获取 {word} 的排名列表超时").into())
+                    }
+                }
+            })
+        }
+    }
+
+    /// 从本地目录读取 `{word}.txt`。
+    pub struct LocalRankListProvider {
+        dir: PathBuf,
+    }
+
+    impl LocalRankListProvider {
+        pub fn new(dir: impl Into<PathBuf>) -> Self {
+            Self { dir: dir.into() }
+        }
+    }
+
+    impl RankListProvider for LocalRankListProvider {
+        fn fetch<'a>(&'a self, word: &'a str) -> RankListFuture<'a> {
+            Box::pin(async move {
+                let path = self.dir.join(format!("{word}.txt"));
+                let body_text = match tokio::fs::read_to_string(&path).await {
+                    Ok(text) => text,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        return Err(RankListNotFound(word.to_string()).into());
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                Ok(parse_rank_list(&body_text))
+            })
+        }
+    }
+
+    /// 内存中的排名列表，可预先从本地目录载入，也可用作测试替身。
+    #[derive(Default)]
+    pub struct MemoryRankListProvider {
+        lists: HashMap<String, Vec<String>>,
+    }
+
+    impl MemoryRankListProvider {
+        pub fn new(lists: HashMap<String, Vec<String>>) -> Self {
+            Self { lists }
+        }
+
+        pub fn from_dir(dir: &Path) -> Result<Self, Box<dyn Error>> {
+            let mut lists = HashMap::new();
+            if dir.exists() {
+                for entry in std::fs::read_dir(dir)? {
+                    let path = entry?.path();
+                    if path.extension().is_none_or(|ext| ext != "txt") {
+                        continue;
+                    }
+                    let Some(word) = path.file_stem().and_then(|s| s.to_str()) else {
+                        continue;
+                    };
+                    let body_text = std::fs::read_to_string(&path)?;
+                    lists.insert(word.to_string(), parse_rank_list(&body_text));
+                }
+            }
+            Ok(Self::new(lists))
+        }
+    }
+
+    impl RankListProvider for MemoryRankListProvider {
+        fn fetch<'a>(&'a self, word: &'a str) -> RankListFuture<'a> {
+            Box::pin(async move {
+                self.lists
+                    .get(word)
+                    .cloned()
+                    .ok_or_else(|| RankListNotFound(word.to_string()).into())
+            })
+        }
+    }
+
+    /// 由词向量计算余弦相似度得出排名列表，只保留词库中的词语。
+    pub struct EmbeddingRankListProvider {
+        model: Arc<EmbeddingModel>,
+    }
+
+    impl EmbeddingRankListProvider {
+        pub fn load(path: &Path, format: EmbeddingFormat) -> Result<Self, Box<dyn Error>> {
+            let mut reader = BufReader::new(File::open(path)?);
+            let model = EmbeddingModel::read(&mut reader, format)?;
+            if model.words.is_empty() {
+                return Err(format!("{} 中没有词库内的词向量", path.display()).into());
+            }
+            Ok(Self {
+                model: Arc::new(model),
+            })
+        }
+
+        pub fn len(&self) -> usize {
+            self.model.words.len()
+        }
+    }
+
+    impl RankListProvider for EmbeddingRankListProvider {
+        fn fetch<'a>(&'a self, word: &'a str) -> RankListFuture<'a> {
+            Box::pin(async move {
+                // 对整个词库计算相似度并排序，放到阻塞线程里以免卡住运行时
+                let model = Arc::clone(&self.model);
+                let target = word.to_string();
+                tokio::task::spawn_blocking(move || model.rank(&target))
+                    .await?
+                    .ok_or_else(|| RankListNotFound(word.to_string()).into())
+            })
+        }
+    }
+
+    /// 归一化后的词向量，按加载顺序连续存放。
+    #[derive(Default)]
+    struct EmbeddingModel {
+        words: Vec<String>,
+        index: HashMap<String, usize>,
+        vectors: Vec<f32>,
+        dim: usize,
+    }
+
+    impl EmbeddingModel {
+        fn read(
+            reader: &mut impl BufRead,
+            format: EmbeddingFormat,
+        ) -> Result<Self, Box<dyn Error>> {
+            let mut model = Self::default();
+            match format {
+                EmbeddingFormat::Text => model.read_text(reader)?,
+                EmbeddingFormat::Binary => model.read_binary(reader)?,
+            }
+            Ok(model)
+        }
+
+        fn read_text(&mut self, reader: &mut impl BufRead) -> Result<(), Box<dyn Error>> {
+            for (line_no, line) in reader.lines().enumerate() {
+                let line = line?;
+                let mut parts = line.split_whitespace();
+                let Some(word) = parts.next() else {
+                    continue;
+                };
+                let values: Vec<&str> = parts.collect();
+                // fastText / word2vec 文本格式首行为 "词数 维度"
+                if line_no == 0 && values.len() == 1 && word.parse::<usize>().is_ok() {
+                    continue;
+                }
+                if !ALL_WORDS.contains(word) {
+                    continue;
+                }
+                let vector = values
+                    .iter()
+                    .map(|v| v.parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|e| format!("第 {} 行词向量无效：{e}", line_no + 1))?;
+                self.push(word.to_string(), vector)?;
+            }
+            Ok(())
+        }
+
+        fn read_binary(&mut self, reader: &mut impl BufRead) -> Result<(), Box<dyn Error>> {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let mut parts = header.split_whitespace();
+            let count: usize = parts.next().ok_or("缺少词数")?.parse()?;
+            let dim: usize = parts.next().ok_or("缺少维度")?.parse()?;
+
+            let mut word_bytes = Vec::new();
+            let mut vector_bytes = vec![0u8; dim * 4];
+            for _ in 0..count {
+                word_bytes.clear();
+                reader.read_until(b' ', &mut word_bytes)?;
+                reader.read_exact(&mut vector_bytes)?;
+                let word = String::from_utf8_lossy(&word_bytes);
+                let word = word.trim();
+                if !ALL_WORDS.contains(word) {
+                    continue;
+                }
+                let vector = vector_bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                self.push(word.to_string(), vector)?;
+            }
+            Ok(())
+        }
+
+        fn push(&mut self, word: String, mut vector: Vec<f32>) -> Result<(), Box<dyn Error>> {
+            if self.dim == 0 {
+                self.dim = vector.len();
+            }
+            if vector.is_empty() || vector.len() != self.dim {
+                return Err(
+                    format!("{word} 的词向量维度为 {}，应为 {}", vector.len(), self.dim).into(),
+                );
+            }
+            if self.index.contains_key(&word) {
+                return Ok(());
+            }
+            let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                vector.iter_mut().for_each(|v| *v /= norm);
+            }
+            self.index.insert(word.clone(), self.words.len());
+            self.words.push(word);
+            self.vectors.extend(vector);
+            Ok(())
+        }
+
+        fn vector(&self, index: usize) -> &[f32] {
+            &self.vectors[index * self.dim..(index + 1) * self.dim]
+        }
+
+        fn rank(&self, word: &str) -> Option<Vec<String>> {
+            let target = self.vector(*self.index.get(word)?);
+            let mut scored: Vec<(f32, usize)> = (0..self.words.len())
+                .map(|i| {
+                    let similarity = self.vector(i).iter().zip(target).map(|(a, b)| a * b).sum();
+                    (similarity, i)
+                })
+                .collect();
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+            Some(
+                scored
+                    .into_iter()
+                    .map(|(_, i)| self.words[i].clone())
+                    .collect(),
+            )
+        }
+    }
+
+    /// 来源中没有该词语的排名列表，重试也不会有。
+    #[derive(Debug)]
+    pub struct RankListNotFound(pub String);
+
+    impl fmt::Display for RankListNotFound {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "没有 {} 的排名列表", self.0)
+        }
+    }
+
+    impl Error for RankListNotFound {}
+
+    /// 目标词语本身不可用（没有排名列表，或列表与目标词语对不上），应换一个词语。
+    /// 列表为空或含有乱码多半是代理、认证页面等临时故障，不算目标词语的问题。
+    pub fn is_unusable_target(e: &(dyn Error + Send + Sync + 'static)) -> bool {
+        e.downcast_ref::<RankListNotFound>().is_some()
+            || matches!(
+                e.downcast_ref::<RankListValidationError>(),
+                Some(
+                    RankListValidationError::TargetMissing
+                        | RankListValidationError::TargetTooLow(_)
+                )
+            )
+    }
+
+    /// 目标词语在排名列表中的最低允许位置（从 1 开始）。
+    const MAX_TARGET_POSITION: usize = 10;
+    /// 排名列表中词库外词语的最高允许占比。
+    const MAX_UNKNOWN_RATIO: f64 = 0.2;
+
+    #[derive(Debug)]
+    pub enum RankListValidationError {
+        Empty,
+        InvalidEntry(String),
+        Duplicate(String),
+        TooManyUnknown { unknown: usize, total: usize },
+        TargetMissing,
+        TargetTooLow(usize),
+    }
+
+    impl fmt::Display for RankListValidationError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::Empty => write!(f, "排名列表为空"),
+                Self::InvalidEntry(entry) => write!(f, "排名列表含有非两字词语：{entry}"),
+                Self::Duplicate(entry) => write!(f, "排名列表含有重复词语：{entry}"),
+                Self::TooManyUnknown { unknown, total } => {
+                    write!(f, "排名列表中 {unknown}/{total} 个词语不在词库中")
+                }
+                Self::TargetMissing => write!(f, "排名列表中没有目标词语"),
+                Self::TargetTooLow(position) => {
+                    write!(
+                        f,
+                        "目标词语在排名列表第 {position} 位，应在前 {MAX_TARGET_POSITION} 位"
+                    )
+                }
+            }
+        }
+    }
+
+    impl Error for RankListValidationError {}
+
+    pub fn validate_rank_list(
+        target: &str,
+        list: &[String],
+    ) -> Result<(), RankListValidationError> {
+        if list.is_empty() {
+            return Err(RankListValidationError::Empty);
+        }
+        let mut seen = HashSet::with_capacity(list.len());
+        let mut unknown = 0;
+        for entry in list {
+            if entry.chars().count() != 2 {
+                return Err(RankListValidationError::InvalidEntry(entry.clone()));
+            }
+            if !seen.insert(entry.as_str()) {
+                return Err(RankListValidationError::Duplicate(entry.clone()));
+            }
+            if !ALL_WORDS.contains(entry) {
+                unknown += 1;
+            }
+        }
+        if unknown as f64 > list.len() as f64 * MAX_UNKNOWN_RATIO {
+            return Err(RankListValidationError::TooManyUnknown {
+                unknown,
+                total: list.len(),
+            });
+        }
+        match list.iter().position(|w| w == target) {
+            None => Err(RankListValidationError::TargetMissing),
+            Some(index) if index >= MAX_TARGET_POSITION => {
+                Err(RankListValidationError::TargetTooLow(index + 1))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// 校验内层来源返回的排名列表，不合格时返回 [`RankListValidationError`]。
+    /// 不合格的列表会从内层缓存中丢弃，丢弃后再向来源获取一次。
+    pub struct ValidatingRankListProvider {
+        inner: Arc<dyn RankListProvider>,
+    }
+
+    impl ValidatingRankListProvider {
+        pub fn new(inner: Arc<dyn RankListProvider>) -> Self {
+            Self { inner }
+        }
+    }
+
+    impl RankListProvider for ValidatingRankListProvider {
+        fn fetch<'a>(&'a self, word: &'a str) -> RankListFuture<'a> {
+            Box::pin(async move {
+                let list = self.inner.fetch(word).await?;
+                let Err(e) = validate_rank_list(word, &list) else {
+                    return Ok(list);
+                };
+                if !self.inner.discard(word).await {
+                    return Err(e.into());
+                }
+                log::warn!("Discarded invalid ciyi rank list for {word}: {e}");
+                let list = self.inner.fetch(word).await?;
+                if let Err(e) = validate_rank_list(word, &list) {
+                    self.inner.discard(word).await;
+                    return Err(e.into());
+                }
+                Ok(list)
+            })
+        }
+    }
+
+    /// 在 `rank_cache/` 下缓存排名列表，命中且未过期时不再访问内层来源，
+    /// 内层来源失败时退回过期缓存。
+    pub struct CachedRankListProvider {
+        inner: Arc<dyn RankListProvider>,
+        dir: PathBuf,
+        compress: bool,
+        ttl: Option<Duration>,
+        max_entries: usize,
+        offline_only: bool,
+    }
+
+    struct CacheEntry {
+        list: Vec<String>,
+        modified: SystemTime,
+    }
+
+    impl CachedRankListProvider {
+        pub fn new(
+            inner: Arc<dyn RankListProvider>,
+            dir: PathBuf,
+            config: &RankCacheConfig,
+        ) -> Self {
+            Self {
+                inner,
+                dir,
+                compress: config.compress,
+                ttl: (config.ttl_hours > 0).then(|| Duration::from_secs(config.ttl_hours * 3600)),
+                max_entries: config.max_entries,
+                offline_only: config.offline_only,
+            }
+        }
+
+        fn plain_path(&self, word: &str) -> PathBuf {
+            self.dir.join(format!("{word}.txt"))
+        }
+
+        fn gz_path(&self, word: &str) -> PathBuf {
+            self.dir.join(format!("{word}.txt.gz"))
+        }
+
+        async fn read(&self, word: &str) -> Option<CacheEntry> {
+            for (path, gz) in [(self.gz_path(word), true), (self.plain_path(word), false)] {
+                let Ok(bytes) = tokio::fs::read(&path).await else {
+                    continue;
+                };
+                let modified = tokio::fs::metadata(&path)
+                    .await
+                    .and_then(|m| m.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                let text = if gz {
+                    let mut text = String::new();
+                    if let Err(e) = GzDecoder::new(bytes.as_slice()).read_to_string(&mut text) {
+                        log::warn!("Corrupt ciyi rank cache {}: {e}", path.display());
+                        continue;
+                    }
+                    text
+                } else {
+                    match String::from_utf8(bytes) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            log::warn!("Corrupt ciyi rank cache {}: {e}", path.display());
+                            continue;
+                        }
+                    }
+                };
+                let list = parse_rank_list(&text);
+                if !list.is_empty() {
+                    return Some(CacheEntry { list, modified });
+                }
+            }
+            None
+        }
+
+        async fn write(&self, word: &str, list: &[String]) -> std::io::Result<()> {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let text = list.join("\n");
+            let (path, stale_path, bytes) = if self.compress {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(text.as_bytes())?;
+                (self.gz_path(word), self.plain_path(word), encoder.finish()?)
+            } else {
+                (self.plain_path(word), self.gz_path(word), text.into_bytes())
+            };
+            let tmp_path = path.with_extension("tmp");
+            tokio::fs::write(&tmp_path, bytes).await?;
+            tokio::fs::rename(&tmp_path, &path).await?;
+            let _ = tokio::fs::remove_file(stale_path).await;
+            Ok(())
+        }
+
+        async fn evict(&self) -> std::io::Result<()> {
+            if self.max_entries == 0 {
+                return Ok(());
+            }
+            let mut files = Vec::new();
+            let mut entries = tokio::fs::read_dir(&self.dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let meta = entry.metadata().await?;
+                if meta.is_file() {
+                    files.push((meta.modified()?, entry.path()));
+                }
+            }
+            if files.len() <= self.max_entries {
+                return Ok(());
+            }
+            files.sort();
+            let excess = files.len() - self.max_entries;
+            for (_, path) in files.into_iter().take(excess) {
+                tokio::fs::remove_file(path).await?;
+            }
+            Ok(())
+        }
+
+        fn is_fresh(&self, entry: &CacheEntry) -> bool {
+            self.ttl
+                .is_none_or(|ttl| entry.modified.elapsed().is_ok_and(|elapsed| elapsed <= ttl))
+        }
+    }
+
+    impl RankListProvider for CachedRankListProvider {
+        fn fetch<'a>(&'a self, word: &'a str) -> RankListFuture<'a> {
+            Box::pin(async move {
+                let cached = self.read(word).await;
+                if let Some(entry) = cached.as_ref()
+                    && (self.offline_only || self.is_fresh(entry))
+                {
+                    return Ok(entry.list.clone());
+                }
+                if self.offline_only {
+                    return Err(format!("离线模式下没有 {word} 的缓存").into());
+                }
+
+                match self.inner.fetch(word).await {
+                    Ok(list) => {
+                        if let Err(e) = self.write(word, &list).await {
+                            log::warn!("Failed to write ciyi rank cache for {word}: {e}");
+                        } else if let Err(e) = self.evict().await {
+                            log::warn!("Failed to evict ciyi rank cache: {e}");
+                        }
+                        Ok(list)
+                    }
+                    Err(e) => match cached {
+                        Some(entry) => {
+                            log::warn!(
+                                "Fetching rank list for {word} failed, using stale cache: {e}"
+                            );
+                            Ok(entry.list)
+                        }
+                        None => Err(e),
+                    },
+                }
+            })
+        }
+
+        fn discard<'a>(&'a self, word: &'a str) -> DiscardFuture<'a> {
+            Box::pin(async move {
+                let mut removed = false;
+                for path in [self.gz_path(word), self.plain_path(word)] {
+                    removed |= tokio::fs::remove_file(path).await.is_ok();
+                }
+                removed
+            })
+        }
+    }
+
+    pub fn build_provider(
+        config: &RankListConfig,
+        cache_config: &RankCacheConfig,
+        data_dir: &Path,
+    ) -> Result<Arc<dyn RankListProvider>, Box<dyn Error>> {
+        let local_dir = data_dir.join(&config.local_dir);
+        let provider: Arc<dyn RankListProvider> = match config.provider {
+            RankListSource::Http => Arc::new(HttpRankListProvider::new(config)?),
+            RankListSource::Local => Arc::new(LocalRankListProvider::new(local_dir)),
+            RankListSource::Memory => {
+                let provider = MemoryRankListProvider::from_dir(&local_dir)?;
+                log::info!(
+                    "Loaded {} ciyi rank lists into memory",
+                    provider.lists.len()
+                );
+                Arc::new(provider)
+            }
+            RankListSource::Embedding => {
+                let path = data_dir.join(&config.embedding_path);
+                let provider = EmbeddingRankListProvider::load(&path, config.embedding_format)?;
+                log::info!(
+                    "Loaded {} ciyi word vectors from {}",
+                    provider.len(),
+                    path.display()
+                );
+                Arc::new(provider)
+            }
+        };
+        // 缓存放在校验之内，缓存命中和离线回退的列表同样经过校验
+        let provider: Arc<dyn RankListProvider> = if cache_config.enabled {
+            Arc::new(CachedRankListProvider::new(
+                provider,
+                data_dir.join("rank_cache"),
+                cache_config,
+            ))
+        } else {
+            provider
+        };
+        Ok(Arc::new(ValidatingRankListProvider::new(provider)))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn words(list: &[&str]) -> Vec<String> {
+            list.iter().map(|w| w.to_string()).collect()
+        }
+
+        /// 词库中的若干词语，第一个为目标。
+        fn known_words(count: usize) -> Vec<String> {
+            let mut list: Vec<String> = ALL_WORDS
+                .iter()
+                .filter(|w| w.chars().count() == 2)
+                .take(count)
+                .cloned()
+                .collect();
+            list.sort();
+            list
+        }
+
+        #[test]
+        fn validate_rank_list_accepts_good_list() {
+            let list = known_words(20);
+            assert!(validate_rank_list(&list[0], &list).is_ok());
+            assert!(validate_rank_list(&list[9], &list).is_ok());
+        }
+
+        #[test]
+        fn validate_rank_list_rejects_bad_lists() {
+            assert!(matches!(
+                validate_rank_list("企业", &[]),
+                Err(RankListValidationError::Empty)
+            ));
+            assert!(matches!(
+                validate_rank_list("企业", &words(&["企业", "企"])),
+                Err(RankListValidationError::InvalidEntry(entry)) if entry == "企"
+            ));
+            assert!(matches!(
+                validate_rank_list("企业", &words(&["企业", "东西", "企业"])),
+                Err(RankListValidationError::Duplicate(entry)) if entry == "企业"
+            ));
+            assert!(matches!(
+                validate_rank_list("企业", &words(&["企业", "东西", "xa", "xb"])),
+                Err(RankListValidationError::TooManyUnknown {
+                    unknown: 2,
+                    total: 4
+                })
+            ));
+            assert!(matches!(
+                validate_rank_list("企业", &words(&["东西"])),
+                Err(RankListValidationError::TargetMissing)
+            ));
+
+            let list = known_words(20);
+            assert!(matches!(
+                validate_rank_list(&list[10], &list),
+                Err(RankListValidationError::TargetTooLow(11))
+            ));
+        }
+
+        #[test]
+        fn missing_lists_are_unusable_targets() {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let memory = MemoryRankListProvider::new(HashMap::new());
+            let e = runtime.block_on(memory.fetch("企业")).unwrap_err();
+            assert!(is_unusable_target(e.as_ref()));
+
+            let dir = std::env::temp_dir().join(format!("ciyi-rank-{}", fastrand::u64(..)));
+            let local = LocalRankListProvider::new(&dir);
+            let e = runtime.block_on(local.fetch("企业")).unwrap_err();
+            assert!(is_unusable_target(e.as_ref()));
+
+            let e: Box<dyn Error + Send + Sync> = "排名服务连续失败，暂停访问中".into();
+            assert!(!is_unusable_target(e.as_ref()));
+        }
+
+        fn embedding(model: EmbeddingModel) -> EmbeddingRankListProvider {
+            EmbeddingRankListProvider {
+                model: Arc::new(model),
+            }
+        }
+
+        #[test]
+        fn embedding_text_skips_header_and_unknown_words() {
+            let text = "4 2\n企业 1 0\nxyz 0 1\n公司 0.9 0.1\n东西 0 1\n企业 0 1\n";
+            let model = EmbeddingModel::read(&mut text.as_bytes(), EmbeddingFormat::Text).unwrap();
+            assert_eq!(model.words, words(&["企业", "公司", "东西"]));
+            assert_eq!(model.dim, 2);
+
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let provider = embedding(model);
+            assert_eq!(
+                runtime.block_on(provider.fetch("企业")).unwrap(),
+                words(&["企业", "公司", "东西"])
+            );
+            assert_eq!(
+                runtime.block_on(provider.fetch("东西")).unwrap(),
+                words(&["东西", "公司", "企业"])
+            );
+            let e = runtime.block_on(provider.fetch("xyz")).unwrap_err();
+            assert!(is_unusable_target(e.as_ref()));
+        }
+
+        #[test]
+        fn embedding_binary_skips_unknown_words() {
+            let mut data = b"3 2\n".to_vec();
+            for (word, vector) in [
+                ("企业", [1.0f32, 0.0]),
+                ("xyz", [0.0, 1.0]),
+                ("东西", [0.0, 1.0]),
+            ] {
+                data.extend(word.as_bytes());
+                data.push(b' ');
+                vector.iter().for_each(|v| data.extend(v.to_le_bytes()));
+                data.push(b'\n');
+            }
+            let model =
+                EmbeddingModel::read(&mut data.as_slice(), EmbeddingFormat::Binary).unwrap();
+            assert_eq!(model.words, words(&["企业", "东西"]));
+            assert_eq!(model.vectors, [1.0, 0.0, 0.0, 1.0]);
+        }
+
+        #[test]
+        fn embedding_rejects_mismatched_dimensions() {
+            let text = "企业 1 0\n公司 1 0 0\n";
+            let e = EmbeddingModel::read(&mut text.as_bytes(), EmbeddingFormat::Text)
+                .err()
+                .unwrap();
+            assert_eq!(e.to_string(), "公司 的词向量维度为 3，应为 2");
+
+            // 二进制格式按首行的维度读取，向量不够长时报错
+            let mut data = b"2 2\n".to_vec();
+            data.extend("企业 ".as_bytes());
+            data.extend(1.0f32.to_le_bytes());
+            assert!(EmbeddingModel::read(&mut data.as_slice(), EmbeddingFormat::Binary).is_err());
+        }
+
+        #[test]
+        fn breaker_lets_one_trial_through_after_cooldown() {
+            let breaker = CircuitBreaker::new(1, Duration::from_secs(3600));
+            breaker.try_acquire().unwrap().record_failure();
+            assert!(breaker.try_acquire().is_none());
+
+            // 冷却为 0 时立即进入半开状态
+            let breaker = CircuitBreaker::new(2, Duration::ZERO);
+            breaker.try_acquire().unwrap().record_failure();
+            let first = breaker.try_acquire().unwrap();
+            first.record_failure();
+            let trial = breaker.try_acquire().unwrap();
+            assert!(breaker.try_acquire().is_none());
+
+            // 试探失败后重新冷却，下一次试探同样只放行一个
+            trial.record_failure();
+            let trial = breaker.try_acquire().unwrap();
+            assert!(breaker.try_acquire().is_none());
+
+            // 试探没有结果就被丢弃（如任务被取消）时归还名额
+            drop(trial);
+            let trial = breaker.try_acquire().unwrap();
+            trial.record_success();
+            let a = breaker.try_acquire().unwrap();
+            let b = breaker.try_acquire().unwrap();
+            drop((a, b));
+            assert!(breaker.try_acquire().is_some());
+        }
+
+        #[test]
+        fn invalid_cached_list_is_discarded_and_refetched() {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+                .unwrap();
+            let list = known_words(20);
+            let target = list[0].clone();
+            let dir = std::env::temp_dir().join(format!("ciyi-rank-{}", fastrand::u64(..)));
+            std::fs::create_dir_all(&dir).unwrap();
+            let cache_file = dir.join(format!("{target}.txt"));
+            std::fs::write(&cache_file, "东西\n企").unwrap();
+
+            let memory =
+                MemoryRankListProvider::new(HashMap::from([(target.clone(), list.clone())]));
+            let cached = CachedRankListProvider::new(
+                Arc::new(memory),
+                dir.clone(),
+                &RankCacheConfig::default(),
+            );
+            let provider = ValidatingRankListProvider::new(Arc::new(cached));
+            assert_eq!(runtime.block_on(provider.fetch(&target)).unwrap(), list);
+            assert_eq!(
+                parse_rank_list(&std::fs::read_to_string(&cache_file).unwrap()),
+                list
+            );
+
+            // 离线时不合格的缓存被删除，不会开始一局坏游戏
+            std::fs::write(&cache_file, "东西\n企").unwrap();
+            let offline = CachedRankListProvider::new(
+                Arc::new(MemoryRankListProvider::new(HashMap::new())),
+                dir.clone(),
+                &RankCacheConfig {
+                    offline_only: true,
+                    ..RankCacheConfig::default()
+                },
+            );
+            let provider = ValidatingRankListProvider::new(Arc::new(offline));
+            assert!(runtime.block_on(provider.fetch(&target)).is_err());
+            assert!(!cache_file.exists());
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+}
+
+mod p_storage {
+    use std::cell::Cell;
+    use std::error::Error;
+    use std::fmt;
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, mpsc};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use kovi::chrono::Local;
+    use kovi::serde_json::{Map, Value};
+    use kovi::{log, serde_json};
+    use tokio::sync::oneshot;
+
+    use crate::ciyi_game::CiYiGameManager;
+    use crate::p_config::{StorageBackend, StorageConfig};
+
+    pub const JSON_FILE_NAME: &str = "ciyi_game_data.json";
+
+    /// JSON 数据文件的当前版本，写入文件的 `schema_version` 字段。
+    pub const SCHEMA_VERSION: u64 = 9;
+
+    type JsonMigration = fn(&mut Map<String, Value>);
+
+    /// 下标 i 处的迁移把数据从版本 i 升级到 i + 1。
+    const JSON_MIGRATIONS: [JsonMigration; SCHEMA_VERSION as usize] = [
+        json_v0_to_v1,
+        json_v1_to_v2,
+        json_v2_to_v3,
+        json_v3_to_v4,
+        json_v4_to_v5,
+        json_v5_to_v6,
+        json_v6_to_v7,
+        json_v7_to_v8,
+        json_v8_to_v9,
+    ];
+
+    /// v0 为没有 `schema_version` 的旧文件，补齐之前靠 `#[serde(default)]` 兼容的字段。
+    fn json_v0_to_v1(data: &mut Map<String, Value>) {
+        data.entry("rejected_words")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Some(Value::Object(states)) = data.get_mut("states") {
+            for state in states.values_mut() {
+                if let Value::Object(state) = state {
+                    state
+                        .entry("direct_guess_enabled")
+                        .or_insert(Value::Bool(false));
+                    state.entry("pending_round").or_insert(Value::Null);
+                    state.entry("round").or_insert(Value::from(0));
+                }
+            }
+        }
+    }
+
+    /// v2 增加已结束轮次的记录。
+    fn json_v1_to_v2(data: &mut Map<String, Value>) {
+        data.entry("round_records")
+            .or_insert_with(|| Value::Array(Vec::new()));
+    }
+
+    /// v3 区分每日首局与加赛局的胜场，此前每天只有一局。
+    fn json_v2_to_v3(data: &mut Map<String, Value>) {
+        if let Some(Value::Array(records)) = data.get_mut("win_records") {
+            for record in records {
+                if let Value::Object(record) = record {
+                    record.entry("first_of_day").or_insert(Value::Bool(true));
+                }
+            }
+        }
+    }
+
+    /// v4 记录每局与每个胜场使用提示的次数。
+    fn json_v3_to_v4(data: &mut Map<String, Value>) {
+        if let Some(Value::Object(states)) = data.get_mut("states") {
+            for state in states.values_mut() {
+                if let Value::Object(state) = state {
+                    state.entry("hints_used").or_insert(Value::from(0));
+                }
+            }
+        }
+        if let Some(Value::Array(records)) = data.get_mut("win_records") {
+            for record in records {
+                if let Value::Object(record) = record {
+                    record.entry("hints_used").or_insert(Value::from(0));
+                }
+            }
+        }
+    }
+
+    /// v5 用逐条的猜测记录代替猜过的词语集合。
+    /// 升级前的猜测不知道是谁猜的，用户记为空，时间记为本局开始的时间。
+    fn json_v4_to_v5(data: &mut Map<String, Value>) {
+        if let Some(Value::Object(states)) = data.get_mut("states") {
+            for state in states.values_mut() {
+                let Value::Object(state) = state else {
+                    continue;
+                };
+                let words = match state.remove("current_guesses") {
+                    Some(Value::Array(words)) => words,
+                    _ => Vec::new(),
+                };
+                let rank_list = state
+                    .get("words_rank_list")
+                    .and_then(|v| v.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                let started_at = state
+                    .get("last_start_time")
+                    .cloned()
+                    .unwrap_or(Value::from(0));
+                let guesses: Vec<Value> = words
+                    .into_iter()
+                    .map(|word| {
+                        let rank = rank_list.iter().position(|w| *w == word).map(|i| i + 1);
+                        serde_json::json!({
+                            "user_id": "",
+                            "username": "未知",
+                            "word": word,
+                            "rank": rank,
+                            "timestamp": started_at,
+                        })
+                    })
+                    .collect();
+                state.insert("guesses".to_string(), Value::Array(guesses));
+            }
+        }
+    }
+
+    /// 升级前的胜场没有猜测明细，按默认基础分计。
+    const LEGACY_WIN_POINTS: u32 = 100;
+
+    /// v6 为胜场记录得分。
+    fn json_v5_to_v6(data: &mut Map<String, Value>) {
+        if let Some(Value::Array(records)) = data.get_mut("win_records") {
+            for record in records {
+                if let Value::Object(record) = record {
+                    record
+                        .entry("points")
+                        .or_insert(Value::from(LEGACY_WIN_POINTS));
+                }
+            }
+        }
+    }
+
+    /// v7 增加助攻记录。
+    fn json_v6_to_v7(data: &mut Map<String, Value>) {
+        data.entry("assist_records")
+            .or_insert_with(|| Value::Array(Vec::new()));
+    }
+
+    /// v8 增加用户的猜测统计与胜场所在一局的猜测次数，升级前的数据无从统计。
+    fn json_v7_to_v8(data: &mut Map<String, Value>) {
+        data.entry("guess_stats")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(Value::Array(records)) = data.get_mut("win_records") {
+            for record in records {
+                if let Value::Object(record) = record {
+                    record.entry("guess_count").or_insert(Value::Null);
+                }
+            }
+        }
+    }
+
+    /// v9 增加赛季存档。
+    fn json_v8_to_v9(data: &mut Map<String, Value>) {
+        data.entry("season_archives")
+            .or_insert_with(|| Value::Array(Vec::new()));
+    }
+
+    /// 数据来自更新版本的插件，不能降级读取。
+    #[derive(Debug)]
+    pub struct SchemaTooNew {
+        pub path: PathBuf,
+        pub found: u64,
+        pub supported: u64,
+    }
+
+    impl fmt::Display for SchemaTooNew {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "{} 的数据版本为 v{}，高于当前插件支持的 v{}，请升级插件",
+                self.path.display(),
+                self.found,
+                self.supported
+            )
+        }
+    }
+
+    impl Error for SchemaTooNew {}
+
+    /// 迁移前把原文件复制为 `{文件名}.v{版本}.bak`。
+    fn backup_before_migration(path: &Path, version: u64) -> std::io::Result<PathBuf> {
+        let mut backup_name = path.as_os_str().to_owned();
+        backup_name.push(format!(".v{version}.bak"));
+        let backup_path = PathBuf::from(backup_name);
+        fs::copy(path, &backup_path)?;
+        Ok(backup_path)
+    }
+    #[cfg(feature = "sqlite")]
+    pub const SQLITE_FILE_NAME: &str = "ciyi_game.db";
+
+    /// 游戏状态与胜场记录的持久化方式。
+    pub trait GameStorage: Send {
+        fn load(&self) -> Result<CiYiGameManager, Box<dyn Error>>;
+        fn save(&self, manager: &CiYiGameManager) -> Result<(), Box<dyn Error>>;
+    }
+
+    pub fn open(
+        data_dir: &Path,
+        backend: StorageBackend,
+        config: &StorageConfig,
+    ) -> Result<Box<dyn GameStorage>, Box<dyn Error>> {
+        let json_path = data_dir.join(JSON_FILE_NAME);
+        match backend {
+            StorageBackend::Json => Ok(Box::new(JsonStorage::new(json_path, config))),
+            #[cfg(feature = "sqlite")]
+            StorageBackend::Sqlite => Ok(Box::new(sqlite::SqliteStorage::open(
+                &data_dir.join(SQLITE_FILE_NAME),
+                &json_path,
+                config,
+            )?)),
+            #[cfg(not(feature = "sqlite"))]
+            StorageBackend::Sqlite => {
+                Err("使用 sqlite 存储需要启用 kovi-plugin-ciyi 的 sqlite 特性".into())
+            }
+        }
+    }
+
+    enum WriteJob {
+        Save(Box<CiYiGameManager>),
+        Flush(oneshot::Sender<()>),
+    }
+
+    /// 在独立线程中写入存储，游戏任务只需交出状态快照。
+    /// 积压的快照只写最新的一份。
+    pub struct StorageWriter {
+        tx: mpsc::Sender<WriteJob>,
+        failed: Arc<AtomicBool>,
+    }
+
+    impl StorageWriter {
+        pub fn spawn(storage: Box<dyn GameStorage>) -> std::io::Result<Self> {
+            let (tx, rx) = mpsc::channel::<WriteJob>();
+            let failed = Arc::new(AtomicBool::new(false));
+            let failed_flag = Arc::clone(&failed);
+            thread::Builder::new()
+                .name("ciyi-storage".to_string())
+                .spawn(move || {
+                    while let Ok(job) = rx.recv() {
+                        let mut latest = None;
+                        let mut flushes = Vec::new();
+                        for job in std::iter::once(job).chain(rx.try_iter()) {
+                            match job {
+                                WriteJob::Save(snapshot) => latest = Some(snapshot),
+                                WriteJob::Flush(done) => flushes.push(done),
+                            }
+                        }
+                        if let Some(snapshot) = latest {
+                            let result = storage.save(&snapshot);
+                            if let Err(e) = &result {
+                                log::error!("Failed to save ciyi game data: {e}");
+                            }
+                            failed_flag.store(result.is_err(), Ordering::Relaxed);
+                        }
+                        for done in flushes {
+                            let _ = done.send(());
+                        }
+                    }
+                })?;
+            Ok(Self { tx, failed })
+        }
+
+        /// 存储线程已退出时返回 false。
+        pub fn save(&self, snapshot: CiYiGameManager) -> bool {
+            self.tx.send(WriteJob::Save(Box::new(snapshot))).is_ok()
+        }
+
+        /// 此前交出的快照写完后，返回的接收端收到通知；存储线程已退出时立即关闭。
+        pub fn flush(&self) -> oneshot::Receiver<()> {
+            let (done_tx, done_rx) = oneshot::channel();
+            let _ = self.tx.send(WriteJob::Flush(done_tx));
+            done_rx
+        }
+
+        /// 上次写入是否失败，读取后清除。
+        pub fn take_failed(&self) -> bool {
+            self.failed.swap(false, Ordering::Relaxed)
+        }
+    }
+
+    /// 先写入临时文件再改名覆盖，写到一半崩溃也不会留下残缺的文件。
+    pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+        let mut tmp_name = path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+        {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(bytes)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, path)
+    }
+
+    /// 整个状态写入同一个 JSON 文件，并在 `backups/` 下保留若干份滚动备份。
+    /// 数据文件损坏时从最新的有效备份恢复。
+    pub struct JsonStorage {
+        path: PathBuf,
+        backup_dir: PathBuf,
+        backup_count: usize,
+        backup_interval: Duration,
+        last_backup: Cell<Option<Instant>>,
+    }
+
+    impl JsonStorage {
+        pub fn new(path: PathBuf, config: &StorageConfig) -> Self {
+            let backup_dir = path.with_file_name("backups");
+            Self {
+                path,
+                backup_dir,
+                backup_count: config.backup_count,
+                backup_interval: Duration::from_secs(config.backup_interval_minutes * 60),
+                last_backup: Cell::new(None),
+            }
+        }
+
+        fn read(path: &Path) -> Result<CiYiGameManager, Box<dyn Error>> {
+            let text = fs::read_to_string(path)?;
+            let mut value: Value = serde_json::from_str(&text)?;
+            let Value::Object(data) = &mut value else {
+                return Err(format!("{} 不是有效的数据文件", path.display()).into());
+            };
+            let version = data
+                .remove("schema_version")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+            if version > SCHEMA_VERSION {
+                return Err(Box::new(SchemaTooNew {
+                    path: path.to_path_buf(),
+                    found: version,
+                    supported: SCHEMA_VERSION,
+                }));
+            }
+            if version < SCHEMA_VERSION {
+                let backup_path = backup_before_migration(path, version)?;
+                for migration in &JSON_MIGRATIONS[version as usize..] {
+                    migration(data);
+                }
+                log::info!(
+                    "Migrated {} from schema v{version} to v{SCHEMA_VERSION}, backup at {}",
+                    path.display(),
+                    backup_path.display()
+                );
+            }
+            Ok(serde_json::from_value(value)?)
+        }
+
+        /// 备份文件按时间命名，文件名排序即时间顺序，最新的在最后。
+        fn backups(&self) -> Vec<PathBuf> {
+            let Ok(entries) = fs::read_dir(&self.backup_dir) else {
+                return Vec::new();
+            };
+            let mut backups: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            backups.sort();
+            backups
+        }
+
+        fn backup(&self) -> std::io::Result<()> {
+            if self.backup_count == 0 || !self.path.exists() {
+                return Ok(());
+            }
+            if self
+                .last_backup
+                .get()
+                .is_some_and(|last| last.elapsed() < self.backup_interval)
+            {
+                return Ok(());
+            }
+            fs::create_dir_all(&self.backup_dir)?;
+            let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+            let name = format!("{stem}.{}.json", Local::now().format("%Y%m%d%H%M%S"));
+            fs::copy(&self.path, self.backup_dir.join(name))?;
+            self.last_backup.set(Some(Instant::now()));
+
+            let backups = self.backups();
+            let excess = backups.len().saturating_sub(self.backup_count);
+            for old in backups.into_iter().take(excess) {
+                fs::remove_file(old)?;
+            }
+            Ok(())
+        }
+
+        fn recover(&self, error: Box<dyn Error>) -> Result<CiYiGameManager, Box<dyn Error>> {
+            log::error!(
+                "Failed to load ciyi game data from {}: {error}",
+                self.path.display()
+            );
+            for backup in self.backups().iter().rev() {
+                match Self::read(backup) {
+                    Ok(manager) => {
+                        let mut corrupt_name = self.path.as_os_str().to_owned();
+                        corrupt_name.push(".corrupt");
+                        fs::rename(&self.path, PathBuf::from(corrupt_name))?;
+                        log::warn!("Recovered ciyi game data from {}", backup.display());
+                        return Ok(manager);
+                    }
+                    Err(e) => log::warn!("Skipping invalid backup {}: {e}", backup.display()),
+                }
+            }
+            Err(error)
+        }
+    }
+
+    impl GameStorage for JsonStorage {
+        fn load(&self) -> Result<CiYiGameManager, Box<dyn Error>> {
+            if !self.path.exists() {
+                return Ok(CiYiGameManager::default());
+            }
+            Self::read(&self.path).or_else(|e| {
+                // 版本过新时备份只会更旧，恢复会丢数据
+                if e.is::<SchemaTooNew>() {
+                    Err(e)
+                } else {
+                    self.recover(e)
+                }
+            })
+        }
+
+        fn save(&self, manager: &CiYiGameManager) -> Result<(), Box<dyn Error>> {
+            if let Err(e) = self.backup() {
+                log::warn!("Failed to back up ciyi game data: {e}");
+            }
+            let mut value = serde_json::to_value(manager)?;
+            if let Value::Object(data) = &mut value {
+                data.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+            }
+            let bytes = serde_json::to_vec_pretty(&value)?;
+            write_atomic(&self.path, &bytes)?;
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    mod sqlite {
+        use std::cell::RefCell;
+        use std::collections::{HashMap, HashSet};
+        use std::error::Error;
+        use std::path::{Path, PathBuf};
+
+        use kovi::chrono::{DateTime, Utc};
+        use kovi::{log, serde_json};
+        use rusqlite::{Connection, params};
+
+        use super::{GameStorage, JsonStorage, SchemaTooNew};
+        use crate::ciyi_game::{
+            AssistRecord, CiYiGameManager, CiYiGameState, GuessRecord, GuessStats, RoundRecord,
+            SeasonArchive, WinRecord,
+        };
+        use crate::p_config::StorageConfig;
+
+        /// 下标 i 处的语句把数据库从版本 i（`PRAGMA user_version`）升级到 i + 1。
+        const MIGRATIONS: &[&str] = &[
+            SCHEMA_V1, SCHEMA_V2, SCHEMA_V3, SCHEMA_V4, SCHEMA_V5, SCHEMA_V6, SCHEMA_V7, SCHEMA_V8,
+            SCHEMA_V9, SCHEMA_V10, SCHEMA_V11,
+        ];
+
+        const SCHEMA_V1: &str = "
+            CREATE TABLE IF NOT EXISTS channels (
+                channel_id           TEXT PRIMARY KEY,
+                current_round        INTEGER NOT NULL,
+                direct_guess_enabled INTEGER NOT NULL,
+                global_history       TEXT NOT NULL,
+                pending_round        TEXT
+            );
+            CREATE TABLE IF NOT EXISTS rounds (
+                channel_id      TEXT NOT NULL,
+                round           INTEGER NOT NULL,
+                target_word     TEXT NOT NULL,
+                started_at      INTEGER NOT NULL,
+                is_finished     INTEGER NOT NULL,
+                words_rank_list TEXT NOT NULL,
+                hints           TEXT NOT NULL,
+                PRIMARY KEY (channel_id, round)
+            );
+            CREATE TABLE IF NOT EXISTS guesses (
+                channel_id TEXT NOT NULL,
+                round      INTEGER NOT NULL,
+                word       TEXT NOT NULL,
+                PRIMARY KEY (channel_id, round, word)
+            );
+            CREATE TABLE IF NOT EXISTS wins (
+                id         INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id    TEXT NOT NULL,
+                username   TEXT NOT NULL,
+                channel_id TEXT NOT NULL,
+                timestamp  INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS wins_channel ON wins (channel_id);
+            CREATE TABLE IF NOT EXISTS rejected_words (
+                word TEXT PRIMARY KEY
+            );
+        ";
+
+        const SCHEMA_V2: &str = "
+            ALTER TABLE rounds ADD COLUMN puzzle_number INTEGER;
+        ";
+
+        const SCHEMA_V3: &str = "
+            CREATE TABLE IF NOT EXISTS round_records (
+                id            INTEGER PRIMARY KEY AUTOINCREMENT,
+                channel_id    TEXT NOT NULL,
+                round         INTEGER NOT NULL,
+                puzzle_number INTEGER,
+                target_word   TEXT NOT NULL,
+                outcome       TEXT NOT NULL,
+                guess_count   INTEGER NOT NULL,
+                started_at    INTEGER NOT NULL,
+                ended_at      INTEGER NOT NULL
+            );
+        ";
+
+        const SCHEMA_V4: &str = "
+            ALTER TABLE wins ADD COLUMN first_of_day INTEGER NOT NULL DEFAULT 1;
+        ";
+
+        const SCHEMA_V5: &str = "
+            ALTER TABLE rounds ADD COLUMN hints_used INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE wins ADD COLUMN hints_used INTEGER NOT NULL DEFAULT 0;
+        ";
+
+        const SCHEMA_V6: &str = "
+            ALTER TABLE guesses ADD COLUMN user_id TEXT NOT NULL DEFAULT '';
+            ALTER TABLE guesses ADD COLUMN username TEXT NOT NULL DEFAULT '未知';
+            ALTER TABLE guesses ADD COLUMN rank INTEGER;
+            ALTER TABLE guesses ADD COLUMN guessed_at INTEGER NOT NULL DEFAULT 0;
+            UPDATE guesses SET
+                rank = (
+                    SELECT j.key + 1 FROM rounds r, json_each(r.words_rank_list) j
+                    WHERE r.channel_id = guesses.channel_id AND r.round = guesses.round
+                      AND j.value = guesses.word
+                ),
+                guessed_at = (
+                    SELECT r.started_at FROM rounds r
+                    WHERE r.channel_id = guesses.channel_id AND r.round = guesses.round
+                );
+        ";
+
+        // 与 JSON 迁移的 LEGACY_WIN_POINTS 一致
+        const SCHEMA_V7: &str = "
+            ALTER TABLE wins ADD COLUMN points INTEGER NOT NULL DEFAULT 100;
+        ";
+
+        const SCHEMA_V8: &str = "
+            CREATE TABLE IF NOT EXISTS assists (
+                id         INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id    TEXT NOT NULL,
+                username   TEXT NOT NULL,
+                channel_id TEXT NOT NULL,
+                timestamp  INTEGER NOT NULL,
+                best_rank  INTEGER NOT NULL,
+                points     INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS assists_channel ON assists (channel_id);
+        ";
+
+        const SCHEMA_V9: &str = "
+            ALTER TABLE wins ADD COLUMN guess_count INTEGER;
+            CREATE TABLE IF NOT EXISTS guess_stats (
+                user_id        TEXT PRIMARY KEY,
+                guesses        INTEGER NOT NULL,
+                ranked_guesses INTEGER NOT NULL,
+                rank_sum       INTEGER NOT NULL
+            );
+        ";
+
+        const SCHEMA_V10: &str = "
+            CREATE TABLE IF NOT EXISTS season_archives (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                season      TEXT NOT NULL,
+                channel_id  TEXT,
+                sort        TEXT NOT NULL,
+                standings   TEXT NOT NULL,
+                archived_at INTEGER NOT NULL
+            );
+        ";
+
+        // 只有当前轮次的排名列表会被读取，历史轮次不再保留副本
+        const SCHEMA_V11: &str = "
+            UPDATE rounds SET words_rank_list = '[]'
+            WHERE round < (
+                SELECT c.current_round FROM channels c WHERE c.channel_id = rounds.channel_id
+            );
+        ";
+
+        /// 内置 SQLite 数据库，频道、轮次、猜测与胜场分表存放。
+        /// 保存时只写入有变化的频道并追加新的记录，排名列表每轮只写一次，
+        /// 换轮后清空上一轮的副本。
+        pub struct SqliteStorage {
+            conn: Connection,
+            /// 各频道上次写入数据库的内容
+            saved: RefCell<HashMap<String, SavedChannel>>,
+        }
+
+        /// `channels` 表中一个频道的内容。预取的一轮以目标词语和获取时间区分，
+        /// 不必为比较而序列化它的排名列表。
+        #[derive(PartialEq)]
+        struct ChannelRow {
+            round: u64,
+            direct_guess_enabled: bool,
+            global_history: String,
+            pending_round: Option<(String, DateTime<Utc>)>,
+        }
+
+        /// `rounds` 表中当前一轮的内容。一轮的排名列表只会从空填充一次，比较长度即可。
+        #[derive(PartialEq)]
+        struct RoundRow {
+            round: u64,
+            target_word: String,
+            started_at: DateTime<Utc>,
+            is_finished: bool,
+            hints: String,
+            puzzle_number: Option<u32>,
+            hints_used: u32,
+            rank_list_len: usize,
+        }
+
+        #[derive(PartialEq)]
+        struct SavedChannel {
+            channel: ChannelRow,
+            round: RoundRow,
+            guesses: usize,
+        }
+
+        impl SavedChannel {
+            fn of(state: &CiYiGameState) -> serde_json::Result<Self> {
+                Ok(Self {
+                    channel: ChannelRow {
+                        round: state.round,
+                        direct_guess_enabled: state.direct_guess_enabled,
+                        global_history: serde_json::to_string(&state.global_history)?,
+                        pending_round: state
+                            .pending_round
+                            .as_ref()
+                            .map(|pending| (pending.target_word.clone(), pending.fetched_at)),
+                    },
+                    round: RoundRow {
+                        round: state.round,
+                        target_word: state.target_word.clone(),
+                        started_at: state.last_start_time,
+                        is_finished: state.is_finished,
+                        hints: serde_json::to_string(&state.hints)?,
+                        puzzle_number: state.puzzle_number,
+                        hints_used: state.hints_used,
+                        rank_list_len: state.words_rank_list.len(),
+                    },
+                    guesses: state.guesses.len(),
+                })
+            }
+        }
+
+        impl SqliteStorage {
+            pub fn open(
+                path: &Path,
+                legacy_json_path: &Path,
+                config: &StorageConfig,
+            ) -> Result<Self, Box<dyn Error>> {
+                let existed = path.exists();
+                let mut conn = Connection::open(path)?;
+                migrate(&mut conn, path, existed)?;
+                let storage = Self {
+                    conn,
+                    saved: RefCell::default(),
+                };
+                storage.migrate_from_json(legacy_json_path, config)?;
+                Ok(storage)
+            }
+
+            /// 数据库为空且存在旧的 JSON 数据时，一次性导入并将 JSON 文件改名。
+            fn migrate_from_json(
+                &self,
+                json_path: &Path,
+                config: &StorageConfig,
+            ) -> Result<(), Box<dyn Error>> {
+                if !json_path.exists() {
+                    return Ok(());
+                }
+                let has_data: bool = self.conn.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM channels) OR EXISTS (SELECT 1 FROM wins)",
+                    [],
+                    |row| row.get(0),
+                )?;
+                if has_data {
+                    return Ok(());
+                }
+                let manager = JsonStorage::new(json_path.to_path_buf(), config).load()?;
+                self.save(&manager)?;
+                let migrated_path = json_path.with_extension("json.migrated");
+                std::fs::rename(json_path, &migrated_path)?;
+                log::info!(
+                    "Migrated ciyi game data from {} to SQLite ({} channels, {} wins)",
+                    json_path.display(),
+                    manager.states.len(),
+                    manager.win_records.len()
+                );
+                Ok(())
+            }
+        }
+
+        fn migrate(
+            conn: &mut Connection,
+            path: &Path,
+            existed: bool,
+        ) -> Result<(), Box<dyn Error>> {
+            let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+            if version > MIGRATIONS.len() {
+                return Err(Box::new(SchemaTooNew {
+                    path: path.to_path_buf(),
+                    found: version as u64,
+                    supported: MIGRATIONS.len() as u64,
+                }));
+            }
+            if version == MIGRATIONS.len() {
+                return Ok(());
+            }
+            if existed {
+                let mut backup_name = path.as_os_str().to_owned();
+                backup_name.push(format!(".v{version}.bak"));
+                let backup_path = PathBuf::from(backup_name);
+                conn.execute("VACUUM INTO ?1", [backup_path.to_string_lossy()])?;
+                log::info!(
+                    "Backed up {} to {} before migration",
+                    path.display(),
+                    backup_path.display()
+                );
+            }
+            for (from, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+                let tx = conn.transaction()?;
+                tx.execute_batch(sql)?;
+                tx.pragma_update(None, "user_version", from + 1)?;
+                tx.commit()?;
+                log::info!("Migrated {} to schema v{}", path.display(), from + 1);
+            }
+            Ok(())
+        }
+
+        fn timestamp(seconds: i64) -> DateTime<Utc> {
+            DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+        }
+
+        impl GameStorage for SqliteStorage {
+            fn load(&self) -> Result<CiYiGameManager, Box<dyn Error>> {
+                let mut manager = CiYiGameManager::default();
+
+                let mut guesses: HashMap<(String, i64), Vec<GuessRecord>> = HashMap::new();
+                let mut stmt = self.conn.prepare(
+                    "SELECT g.channel_id, g.round, g.user_id, g.username, g.word, g.rank,
+                            g.guessed_at
+                     FROM guesses g
+                     JOIN channels c ON c.channel_id = g.channel_id AND c.current_round = g.round
+                     ORDER BY g.guessed_at, g.rowid",
+                )?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    let key = (row.get::<_, String>(0)?, row.get::<_, i64>(1)?);
+                    guesses.entry(key).or_default().push(GuessRecord {
+                        user_id: row.get(2)?,
+                        username: row.get(3)?,
+                        word: row.get(4)?,
+                        rank: row.get::<_, Option<i64>>(5)?.map(|rank| rank as usize),
+                        timestamp: timestamp(row.get(6)?),
+                    });
+                }
+
+                let mut stmt = self.conn.prepare(
+                    "SELECT c.channel_id, c.current_round, c.direct_guess_enabled,
+                            c.global_history, c.pending_round, r.target_word, r.started_at,
+                            r.is_finished, r.words_rank_list, r.hints, r.puzzle_number,
+                            r.hints_used
+                     FROM channels c
+                     JOIN rounds r ON r.channel_id = c.channel_id AND r.round = c.current_round",
+                )?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    let channel_id: String = row.get(0)?;
+                    let round: i64 = row.get(1)?;
+                    let pending_round: Option<String> = row.get(4)?;
+                    let state = CiYiGameState {
+                        channel_id: channel_id.clone(),
+                        target_word: row.get(5)?,
+                        last_start_time: timestamp(row.get(6)?),
+                        global_history: serde_json::from_str(&row.get::<_, String>(3)?)?,
+                        guesses: guesses
+                            .remove(&(channel_id.clone(), round))
+                            .unwrap_or_default(),
+                        words_rank_list: serde_json::from_str(&row.get::<_, String>(8)?)?,
+                        hints: serde_json::from_str(&row.get::<_, String>(9)?)?,
+                        hints_used: row.get(11)?,
+                        is_finished: row.get(7)?,
+                        direct_guess_enabled: row.get(2)?,
+                        pending_round: pending_round
+                            .map(|p| serde_json::from_str(&p))
+                            .transpose()?,
+                        round: round as u64,
+                        puzzle_number: row.get(10)?,
+                        give_up_votes: HashSet::new(),
+                    };
+                    manager.states.insert(channel_id, state);
+                }
+
+                let mut stmt = self.conn.prepare(
+                    "SELECT user_id, username, channel_id, timestamp, first_of_day, hints_used,
+                            points, guess_count
+                     FROM wins ORDER BY id",
+                )?;
+                let rows = stmt.query_map([], |row| {
+                    Ok(WinRecord {
+                        user_id: row.get(0)?,
+                        username: row.get(1)?,
+                        channel_id: row.get(2)?,
+                        timestamp: timestamp(row.get(3)?),
+                        first_of_day: row.get(4)?,
+                        hints_used: row.get(5)?,
+                        points: row.get(6)?,
+                        guess_count: row.get::<_, Option<i64>>(7)?.map(|n| n as usize),
+                    })
+                })?;
+                for row in rows {
+                    manager.win_records.push(row?);
+                }
+
+                let mut stmt = self.conn.prepare(
+                    "SELECT channel_id, round, puzzle_number, target_word, outcome, guess_count,
+                            started_at, ended_at
+                     FROM round_records ORDER BY id",
+                )?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    manager.round_records.push(RoundRecord {
+                        channel_id: row.get(0)?,
+                        round: row.get::<_, i64>(1)? as u64,
+                        puzzle_number: row.get(2)?,
+                        target_word: row.get(3)?,
+                        outcome: serde_json::from_str(&row.get::<_, String>(4)?)?,
+                        guess_count: row.get::<_, i64>(5)? as usize,
+                        started_at: timestamp(row.get(6)?),
+                        ended_at: timestamp(row.get(7)?),
+                    });
+                }
+
+                let mut stmt = self.conn.prepare(
+                    "SELECT user_id, username, channel_id, timestamp, best_rank, points
+                     FROM assists ORDER BY id",
+                )?;
+                let rows = stmt.query_map([], |row| {
+                    Ok(AssistRecord {
+                        user_id: row.get(0)?,
+                        username: row.get(1)?,
+                        channel_id: row.get(2)?,
+                        timestamp: timestamp(row.get(3)?),
+                        best_rank: row.get::<_, i64>(4)? as usize,
+                        points: row.get(5)?,
+                    })
+                })?;
+                for row in rows {
+                    manager.assist_records.push(row?);
+                }
+
+                let mut stmt = self.conn.prepare(
+                    "SELECT user_id, guesses, ranked_guesses, rank_sum FROM guess_stats",
+                )?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    manager.guess_stats.insert(
+                        row.get(0)?,
+                        GuessStats {
+                            guesses: row.get::<_, i64>(1)? as u64,
+                            ranked_guesses: row.get::<_, i64>(2)? as u64,
+                            rank_sum: row.get::<_, i64>(3)? as u64,
+                        },
+                    );
+                }
+
+                let mut stmt = self.conn.prepare(
+                    "SELECT season, channel_id, sort, standings, archived_at
+                     FROM season_archives ORDER BY id",
+                )?;
+                let mut rows = stmt.query([])?;
+                while let Some(row) = rows.next()? {
+                    manager.season_archives.push(SeasonArchive {
+                        season: serde_json::from_str(&row.get::<_, String>(0)?)?,
+                        channel_id: row.get(1)?,
+                        sort: serde_json::from_str(&row.get::<_, String>(2)?)?,
+                        standings: serde_json::from_str(&row.get::<_, String>(3)?)?,
+                        archived_at: timestamp(row.get(4)?),
+                    });
+                }
+
+                let mut stmt = self.conn.prepare("SELECT word FROM rejected_words")?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                for row in rows {
+                    manager.rejected_words.insert(row?);
+                }
+
+                let mut saved = HashMap::with_capacity(manager.states.len());
+                for (channel_id, state) in &manager.states {
+                    saved.insert(channel_id.clone(), SavedChannel::of(state)?);
+                }
+                *self.saved.borrow_mut() = saved;
+
+                Ok(manager)
+            }
+
+            fn save(&self, manager: &CiYiGameManager) -> Result<(), Box<dyn Error>> {
+                let tx = self.conn.unchecked_transaction()?;
+                let saved = self.saved.borrow();
+                let mut written = Vec::new();
 
-# 插件配置
-[plugin]
+                for state in manager.states.values() {
+                    let current = SavedChannel::of(state)?;
+                    let previous = saved.get(&state.channel_id);
+                    if previous == Some(&current) {
+                        continue;
+                    }
+                    let round = state.round as i64;
+
+                    if previous.is_none_or(|p| p.channel != current.channel) {
+                        tx.execute(
+                            "INSERT INTO channels (channel_id, current_round, direct_guess_enabled,
+                                                   global_history, pending_round)
+                             VALUES (?1, ?2, ?3, ?4, ?5)
+                             ON CONFLICT (channel_id) DO UPDATE SET
+                                 current_round = excluded.current_round,
+                                 direct_guess_enabled = excluded.direct_guess_enabled,
+                                 global_history = excluded.global_history,
+                                 pending_round = excluded.pending_round",
+                            params![
+                                state.channel_id,
+                                round,
+                                state.direct_guess_enabled,
+                                current.channel.global_history,
+                                state
+                                    .pending_round
+                                    .as_ref()
+                                    .map(serde_json::to_string)
+                                    .transpose()?,
+                            ],
+                        )?;
+                    }
 
-# 只有 @ Bot 时才回复
-only_at = false
+                    let new_round = previous.is_none_or(|p| p.round.round != current.round.round);
+                    let rank_list_changed = new_round
+                        || previous
+                            .is_some_and(|p| p.round.rank_list_len != current.round.rank_list_len);
+                    if rank_list_changed {
+                        tx.execute(
+                            "INSERT INTO rounds (channel_id, round, target_word, started_at,
+                                                 is_finished, words_rank_list, hints,
+                                                 puzzle_number, hints_used)
+                             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                             ON CONFLICT (channel_id, round) DO UPDATE SET
+                                 target_word = excluded.target_word,
+                                 started_at = excluded.started_at,
+                                 is_finished = excluded.is_finished,
+                                 words_rank_list = excluded.words_rank_list,
+                                 hints = excluded.hints,
+                                 puzzle_number = excluded.puzzle_number,
+                                 hints_used = excluded.hints_used",
+                            params![
+                                state.channel_id,
+                                round,
+                                state.target_word,
+                                state.last_start_time.timestamp(),
+                                state.is_finished,
+                                serde_json::to_string(&state.words_rank_list)?,
+                                current.round.hints,
+                                state.puzzle_number,
+                                state.hints_used,
+                            ],
+                        )?;
+                        tx.execute(
+                            "UPDATE rounds SET words_rank_list = '[]'
+                             WHERE channel_id = ?1 AND round < ?2 AND words_rank_list != '[]'",
+                            params![state.channel_id, round],
+                        )?;
+                    } else if previous.is_none_or(|p| p.round != current.round) {
+                        tx.execute(
+                            "UPDATE rounds SET
+                                 target_word = ?3,
+                                 started_at = ?4,
+                                 is_finished = ?5,
+                                 hints = ?6,
+                                 puzzle_number = ?7,
+                                 hints_used = ?8
+                             WHERE channel_id = ?1 AND round = ?2",
+                            params![
+                                state.channel_id,
+                                round,
+                                state.target_word,
+                                state.last_start_time.timestamp(),
+                                state.is_finished,
+                                current.round.hints,
+                                state.puzzle_number,
+                                state.hints_used,
+                            ],
+                        )?;
+                    }
 
-# 指令前缀 示例：["!", "。"]
-prefixes = []
+                    // 猜测只会追加，同一轮中已写入的前缀不必再写
+                    let saved_guesses = match previous {
+                        Some(p) if !new_round => p.guesses,
+                        _ => 0,
+                    };
+                    let mut stmt = tx.prepare_cached(
+                        "INSERT OR IGNORE INTO guesses (channel_id, round, word, user_id, username,
+                                                        rank, guessed_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    )?;
+                    for guess in state.guesses.iter().skip(saved_guesses) {
+                        stmt.execute(params![
+                            state.channel_id,
+                            round,
+                            guess.word,
+                            guess.user_id,
+                            guess.username,
+                            guess.rank.map(|rank| rank as i64),
+                            guess.timestamp.timestamp(),
+                        ])?;
+                    }
+                    written.push((state.channel_id.clone(), current));
+                }
+                drop(saved);
+
+                // 胜场只会追加，数据库中已有的条数即为已保存的前缀
+                let saved: i64 = tx.query_row("SELECT COUNT(*) FROM wins", [], |row| row.get(0))?;
+                {
+                    let mut stmt = tx.prepare_cached(
+                        "INSERT INTO wins (user_id, username, channel_id, timestamp,
+                                           first_of_day, hints_used, points, guess_count)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    )?;
+                    for record in manager.win_records.iter().skip(saved as usize) {
+                        stmt.execute(params![
+                            record.user_id,
+                            record.username,
+                            record.channel_id,
+                            record.timestamp.timestamp(),
+                            record.first_of_day,
+                            record.hints_used,
+                            record.points,
+                            record.guess_count.map(|n| n as i64),
+                        ])?;
+                    }
+                }
 
-# Bot 响应时 @ 用户
-at_user = false
+                let saved: i64 =
+                    tx.query_row("SELECT COUNT(*) FROM round_records", [], |row| row.get(0))?;
+                {
+                    let mut stmt = tx.prepare_cached(
+                        "INSERT INTO round_records (channel_id, round, puzzle_number, target_word,
+                                                    outcome, guess_count, started_at, ended_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    )?;
+                    for record in manager.round_records.iter().skip(saved as usize) {
+                        stmt.execute(params![
+                            record.channel_id,
+                            record.round as i64,
+                            record.puzzle_number,
+                            record.target_word,
+                            serde_json::to_string(&record.outcome)?,
+                            record.guess_count as i64,
+                            record.started_at.timestamp(),
+                            record.ended_at.timestamp(),
+                        ])?;
+                    }
+                }
 
-# Bot 响应时引用用户消息
-quote_user = true
+                let saved: i64 =
+                    tx.query_row("SELECT COUNT(*) FROM assists", [], |row| row.get(0))?;
+                {
+                    let mut stmt = tx.prepare_cached(
+                        "INSERT INTO assists (user_id, username, channel_id, timestamp,
+                                              best_rank, points)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    )?;
+                    for record in manager.assist_records.iter().skip(saved as usize) {
+                        stmt.execute(params![
+                            record.user_id,
+                            record.username,
+                            record.channel_id,
+                            record.timestamp.timestamp(),
+                            record.best_rank as i64,
+                            record.points,
+                        ])?;
+                    }
+                }
 
-# 是否开启直接猜测模式（不需要指令，直接发送两字词语即可猜测）
-direct_guess = false
+                let saved: i64 =
+                    tx.query_row("SELECT COUNT(*) FROM season_archives", [], |row| row.get(0))?;
+                {
+                    let mut stmt = tx.prepare_cached(
+                        "INSERT INTO season_archives (season, channel_id, sort, standings,
+                                                      archived_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                    )?;
+                    for archive in manager.season_archives.iter().skip(saved as usize) {
+                        stmt.execute(params![
+                            serde_json::to_string(&archive.season)?,
+                            archive.channel_id,
+                            serde_json::to_string(&archive.sort)?,
+                            serde_json::to_string(&archive.standings)?,
+                            archive.archived_at.timestamp(),
+                        ])?;
+                    }
+                }
 
-# 提示中显示几个历史记录
-history_display = 10
+                {
+                    let mut stmt = tx.prepare_cached(
+                        "INSERT INTO guess_stats (user_id, guesses, ranked_guesses, rank_sum)
+                         VALUES (?1, ?2, ?3, ?4)
+                         ON CONFLICT (user_id) DO UPDATE SET
+                             guesses = excluded.guesses,
+                             ranked_guesses = excluded.ranked_guesses,
+                             rank_sum = excluded.rank_sum",
+                    )?;
+                    for (user_id, stats) in &manager.guess_stats {
+                        stmt.execute(params![
+                            user_id,
+                            stats.guesses as i64,
+                            stats.ranked_guesses as i64,
+                            stats.rank_sum as i64,
+                        ])?;
+                    }
+                }
 
-# 排行榜显示几个人
-rank_display = 10
-"#;
+                {
+                    let mut stmt = tx.prepare_cached(
+                        "INSERT OR IGNORE INTO rejected_words (word) VALUES (?1)",
+                    )?;
+                    for word in &manager.rejected_words {
+                        stmt.execute(params![word])?;
+                    }
+                }
 
-    /// [channel]
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct ChannelConfig {
-        pub white: Vec<String>,
-        pub black: Vec<String>,
-    }
+                tx.commit()?;
+                self.saved.borrow_mut().extend(written);
+                Ok(())
+            }
+        }
 
-    /// [plugin]
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct PluginConfig {
-        pub only_at: bool,
-        pub prefixes: Vec<String>,
-        pub at_user: bool,
-        pub quote_user: bool,
-        pub direct_guess: bool,
-        pub history_display: usize,
-        pub rank_display: usize,
-    }
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::p_storage::tests::TempDir;
+
+            #[test]
+            fn unversioned_database_is_migrated_with_backup() {
+                let dir = TempDir::new();
+                let path = dir.0.join("ciyi_game.db");
+                {
+                    let conn = Connection::open(&path).unwrap();
+                    conn.execute_batch(SCHEMA_V1).unwrap();
+                    conn.execute_batch(
+                        r#"
+                        INSERT INTO channels VALUES ('123', 0, 0, '["企业"]', NULL);
+                        INSERT INTO rounds VALUES
+                            ('123', 0, '企业', 1735689600, 0, '["企业","公司","东西"]', '[]');
+                        INSERT INTO guesses VALUES ('123', 0, '东西');
+                        INSERT INTO wins (user_id, username, channel_id, timestamp)
+                            VALUES ('1', '甲', '123', 1735600000);
+                        "#,
+                    )
+                    .unwrap();
+                }
 
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct Config {
-        pub channel: ChannelConfig,
-        pub plugin: PluginConfig,
+                let storage =
+                    SqliteStorage::open(&path, &dir.0.join("none.json"), &StorageConfig::default())
+                        .unwrap();
+                assert!(dir.0.join("ciyi_game.db.v0.bak").exists());
+
+                let manager = storage.load().unwrap();
+                let guess = &manager.states["123"].guesses[0];
+                assert_eq!(guess.word, "东西");
+                assert_eq!(guess.rank, Some(3));
+                assert_eq!(guess.timestamp.timestamp(), 1735689600);
+                let win = &manager.win_records[0];
+                assert!(win.first_of_day);
+                assert_eq!(win.points, 100);
+                assert_eq!(win.guess_count, None);
+
+                storage.save(&manager).unwrap();
+                assert_eq!(storage.load().unwrap().win_records.len(), 1);
+            }
 
-        #[serde(skip)]
-        config_file_path: PathBuf,
-    }
+            fn state(round: u64, history: &[&str], rank_list: &[&str]) -> CiYiGameState {
+                CiYiGameState {
+                    channel_id: "123".into(),
+                    target_word: rank_list[0].into(),
+                    last_start_time: Utc::now(),
+                    global_history: history.iter().map(|w| w.to_string()).collect(),
+                    guesses: Vec::new(),
+                    words_rank_list: rank_list.iter().map(|w| w.to_string()).collect(),
+                    hints: Vec::new(),
+                    is_finished: false,
+                    direct_guess_enabled: false,
+                    pending_round: None,
+                    round,
+                    puzzle_number: None,
+                    hints_used: 0,
+                    give_up_votes: HashSet::new(),
+                }
+            }
 
-    impl Config {
-        pub fn new(data_dir: PathBuf) -> Result<Self, Box<dyn Error>> {
-            if !data_dir.exists() {
-                std::fs::create_dir_all(&data_dir)?;
+            #[test]
+            fn rank_lists_are_written_once_per_round() {
+                let dir = TempDir::new();
+                let storage = SqliteStorage::open(
+                    &dir.0.join("ciyi_game.db"),
+                    &dir.0.join("none.json"),
+                    &StorageConfig::default(),
+                )
+                .unwrap();
+                let rank_list = |round: i64| -> String {
+                    storage
+                        .conn
+                        .query_row(
+                            "SELECT words_rank_list FROM rounds
+                             WHERE channel_id = '123' AND round = ?1",
+                            [round],
+                            |row| row.get(0),
+                        )
+                        .unwrap()
+                };
+
+                let mut manager = CiYiGameManager::default();
+                manager
+                    .states
+                    .insert("123".into(), state(0, &["企业"], &["企业", "公司", "东西"]));
+                storage.save(&manager).unwrap();
+
+                // 直接改写库中的数据，之后的保存若重写了它们就能看出来
+                storage
+                    .conn
+                    .execute_batch(
+                        r#"
+                        UPDATE rounds SET words_rank_list = '["企业"]';
+                        UPDATE channels SET direct_guess_enabled = 1;
+                        "#,
+                    )
+                    .unwrap();
+                manager
+                    .states
+                    .get_mut("123")
+                    .unwrap()
+                    .guesses
+                    .push(GuessRecord {
+                        user_id: "1".into(),
+                        username: "甲".into(),
+                        word: "东西".into(),
+                        rank: Some(3),
+                        timestamp: Utc::now(),
+                    });
+                storage.save(&manager).unwrap();
+                assert_eq!(rank_list(0), r#"["企业"]"#);
+                let loaded = storage.load().unwrap();
+                assert_eq!(loaded.states["123"].guesses.len(), 1);
+                assert!(loaded.states["123"].direct_guess_enabled);
+
+                // 没有变化时什么也不写
+                storage.conn.execute("DELETE FROM guesses", []).unwrap();
+                storage.save(&loaded).unwrap();
+                assert!(storage.load().unwrap().states["123"].guesses.is_empty());
+
+                // 换轮时写入新一轮的排名列表，并清空上一轮的副本
+                manager
+                    .states
+                    .insert("123".into(), state(1, &["企业", "公司"], &["公司", "企业"]));
+                storage.save(&manager).unwrap();
+                assert_eq!(rank_list(0), "[]");
+                assert_eq!(rank_list(1), r#"["公司","企业"]"#);
+                let loaded = storage.load().unwrap();
+                assert_eq!(loaded.states["123"].round, 1);
+                assert_eq!(loaded.states["123"].words_rank_list, ["公司", "企业"]);
+                assert!(!loaded.states["123"].direct_guess_enabled);
             }
 
-            let config_file_path = data_dir.join("config.toml");
+            #[test]
+            fn newer_database_is_refused() {
+                let dir = TempDir::new();
+                let path = dir.0.join("ciyi_game.db");
+                Connection::open(&path)
+                    .unwrap()
+                    .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+                    .unwrap();
+
+                let e =
+                    SqliteStorage::open(&path, &dir.0.join("none.json"), &StorageConfig::default())
+                        .err()
+                        .expect("newer schema must be refused");
+                assert!(e.is::<SchemaTooNew>());
+                assert_eq!(std::fs::read_dir(&dir.0).unwrap().count(), 1);
+            }
+        }
+    }
 
-            let default_config: Config = toml::from_str(DEFAULT_CONFIG_STR)?;
+    #[cfg(test)]
+    mod tests {
+        use super::*;
 
-            let mut config: Config = load_toml_data(default_config, config_file_path.clone())?;
+        /// 测试结束时删除的临时目录。
+        pub(super) struct TempDir(pub PathBuf);
 
-            config.config_file_path = config_file_path;
+        impl TempDir {
+            pub fn new() -> Self {
+                let dir = std::env::temp_dir().join(format!("ciyi-test-{}", fastrand::u64(..)));
+                fs::create_dir_all(&dir).unwrap();
+                Self(dir)
+            }
+        }
 
-            Ok(config)
+        impl Drop for TempDir {
+            fn drop(&mut self) {
+                let _ = fs::remove_dir_all(&self.0);
+            }
         }
-    }
-}
 
-mod p_const {
-    use kovi::serde_json;
-    use once_cell::sync::Lazy;
-    use std::collections::HashSet;
+        /// 未加版本号之前的插件写出的数据文件。
+        const BASELINE_DATA: &str = r#"{
+  "states": {
+    "123": {
+      "channel_id": "123",
+      "target_word": "企业",
+      "last_start_time": 1735689600,
+      "global_history": ["企业"],
+      "current_guesses": ["东西", "哀悼"],
+      "words_rank_list": ["企业", "公司", "东西"],
+      "hints": [{"text": "东西", "rank": 3}],
+      "is_finished": false
+    }
+  },
+  "win_records": [
+    {"user_id": "1", "username": "甲", "channel_id": "123", "timestamp": 1735600000}
+  ]
+}"#;
+
+        #[test]
+        fn baseline_json_is_migrated_with_backup() {
+            let dir = TempDir::new();
+            let path = dir.0.join(JSON_FILE_NAME);
+            fs::write(&path, BASELINE_DATA).unwrap();
+
+            let storage = JsonStorage::new(path.clone(), &StorageConfig::default());
+            let manager = storage.load().unwrap();
+
+            let backup = dir.0.join(format!("{JSON_FILE_NAME}.v0.bak"));
+            assert_eq!(fs::read_to_string(backup).unwrap(), BASELINE_DATA);
+
+            let state = &manager.states["123"];
+            assert_eq!(state.round, 0);
+            assert!(!state.direct_guess_enabled);
+            let ranks: Vec<(&str, Option<usize>)> = state
+                .guesses
+                .iter()
+                .map(|g| (g.word.as_str(), g.rank))
+                .collect();
+            assert_eq!(ranks.len(), 2);
+            assert!(ranks.contains(&("东西", Some(3))));
+            assert!(ranks.contains(&("哀悼", None)));
+            assert!(state.guesses.iter().all(|g| g.user_id.is_empty()));
+            let win = &manager.win_records[0];
+            assert!(win.first_of_day);
+            assert_eq!(win.points, LEGACY_WIN_POINTS);
+            assert_eq!(win.guess_count, None);
+            assert!(manager.round_records.is_empty());
+            assert!(manager.season_archives.is_empty());
+            assert!(manager.rejected_words.is_empty());
+
+            // 保存后写入当前版本，再次读取不再迁移
+            storage.save(&manager).unwrap();
+            let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            assert_eq!(saved["schema_version"], Value::from(SCHEMA_VERSION));
+            assert_eq!(storage.load().unwrap().states["123"].guesses.len(), 2);
+        }
 
-    const ALL_WORDS_JSON: &str = include_str!("../res/all_words.json");
-    const QUESTION_WORDS_JSON: &str = include_str!("../res/question_words.json");
+        #[test]
+        fn newer_json_schema_is_refused_untouched() {
+            let dir = TempDir::new();
+            let path = dir.0.join(JSON_FILE_NAME);
+            let text = format!(
+                r#"{{"schema_version": {}, "states": {{}}, "win_records": []}}"#,
+                SCHEMA_VERSION + 1
+            );
+            fs::write(&path, &text).unwrap();
+
+            let e = JsonStorage::new(path.clone(), &StorageConfig::default())
+                .load()
+                .err()
+                .expect("newer schema must be refused");
+            let too_new = e.downcast_ref::<SchemaTooNew>().expect("SchemaTooNew");
+            assert_eq!(too_new.found, SCHEMA_VERSION + 1);
+            assert_eq!(fs::read_to_string(&path).unwrap(), text);
+            assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 1);
+        }
 
-    pub static ALL_WORDS: Lazy<HashSet<String>> = Lazy::new(|| {
-        let words: Vec<String> =
-            serde_json::from_str(ALL_WORDS_JSON).expect("Failed to parse all_words.json");
-        words.into_iter().collect()
-    });
+        #[test]
+        fn writer_saves_latest_snapshot() {
+            let dir = TempDir::new();
+            let config = StorageConfig::default();
+            let storage = open(&dir.0, StorageBackend::Json, &config).unwrap();
+            let writer = StorageWriter::spawn(storage).unwrap();
+            for n in 0..3 {
+                let mut manager = CiYiGameManager::default();
+                manager.rejected_words.insert(format!("词{n}"));
+                assert!(writer.save(manager));
+            }
+            writer.flush().blocking_recv().unwrap();
+            assert!(!writer.take_failed());
 
-    pub static QUESTION_WORDS: Lazy<Vec<String>> = Lazy::new(|| {
-        serde_json::from_str(QUESTION_WORDS_JSON).expect("Failed to parse question_words.json")
-    });
+            let storage = open(&dir.0, StorageBackend::Json, &config).unwrap();
+            let loaded = storage.load().unwrap();
+            assert_eq!(loaded.rejected_words.len(), 1);
+            assert!(loaded.rejected_words.contains("词2"));
+        }
+    }
 }
 
 mod p_fn {
-    use std::sync::{Arc, Mutex};
+    use std::sync::Arc;
 
-    use kovi::{Message, MsgEvent};
+    use kovi::chrono::NaiveDate;
+    use kovi::{Message, MsgEvent, log};
 
     use crate::{
-        ciyi_game::{self, CiYiGameManager, FetchedData},
-        p_command, p_config,
+        ciyi_game::{
+            CiYiGameManager, FetchRequest, FetchedData, LeaderboardQuery, Season, TimeWindow,
+        },
+        p_actor::{GameHandle, GameUnavailable},
+        p_command,
+        p_config::{self, LeaderboardSort},
+        p_rank::{self, RankListProvider},
     };
 
+    const MAX_FETCH_ATTEMPTS: usize = 3;
+
     pub fn show_commands() -> String {
         let config = p_config::config();
         let command = p_command::commands();
@@ -639,6 +6255,7 @@ mod p_fn {
 
 周期
     每日一词，猜对则次日刷新
+    猜不出时可放弃，公布答案后次日刷新
     系统记录猜对次数，可查排行"
             .to_string()
     }
@@ -646,7 +6263,8 @@ mod p_fn {
     pub async fn guess_word(
         event: &Arc<MsgEvent>,
         params: &[&str],
-        game_manager_mutex: &Arc<Mutex<CiYiGameManager>>,
+        game: &GameHandle,
+        provider: &dyn RankListProvider,
     ) -> String {
         if params.is_empty() || params[0].chars().count() != 2 {
             return format!("无效输入：{}", params[0]);
@@ -655,31 +6273,258 @@ mod p_fn {
         let guess_word = params[0].to_string();
         let group_id = event.group_id.unwrap().to_string();
         let user_id = event.user_id.to_string();
-        let username = event
-            .sender
-            .nickname
-            .clone()
-            .unwrap_or_else(|| event.sender.user_id.to_string());
+        let username = sender_name(event);
 
-        let fetch_request = {
-            let manager = game_manager_mutex.lock().unwrap();
-            manager.prepare_guess(&group_id)
+        try_guess_word(game, provider, group_id, user_id, username, guess_word)
+            .await
+            .unwrap_or_else(|e| e.to_string())
+    }
+
+    async fn try_guess_word(
+        game: &GameHandle,
+        provider: &dyn RankListProvider,
+        group_id: String,
+        user_id: String,
+        username: String,
+        guess_word: String,
+    ) -> Result<String, GameUnavailable> {
+        fetch_and_commit(
+            game,
+            provider,
+            group_id,
+            CiYiGameManager::prepare_guess,
+            move |manager, group_id, fetched_data| {
+                manager.commit_guess(group_id, &user_id, &username, guess_word, fetched_data)
+            },
+        )
+        .await
+    }
+
+    pub async fn new_round(
+        event: &Arc<MsgEvent>,
+        game: &GameHandle,
+        provider: &dyn RankListProvider,
+    ) -> String {
+        let group_id = event.group_id.unwrap().to_string();
+        fetch_and_commit(
+            game,
+            provider,
+            group_id,
+            CiYiGameManager::prepare_new_round,
+            CiYiGameManager::commit_new_round,
+        )
+        .await
+        .unwrap_or_else(|e| e.to_string())
+    }
+
+    /// 按 `prepare` 获取排名列表后交给 `commit` 提交。
+    async fn fetch_and_commit<C, R>(
+        game: &GameHandle,
+        provider: &dyn RankListProvider,
+        channel_id: String,
+        prepare: fn(&CiYiGameManager, &str) -> Option<FetchRequest>,
+        commit: C,
+    ) -> Result<R, GameUnavailable>
+    where
+        C: FnOnce(&mut CiYiGameManager, &str, Option<FetchedData>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        // 同一频道只有一个请求在获取，其余请求等它提交后再判断是否仍需获取
+        let fetch_lock = {
+            let channel_id = channel_id.clone();
+            game.call(move |manager| {
+                prepare(manager, &channel_id)
+                    .is_some()
+                    .then(|| manager.fetch_lock(&channel_id))
+            })
+            .await?
+        };
+        let _fetch_guard = match &fetch_lock {
+            Some(lock) => Some(lock.lock().await),
+            None => None,
         };
 
-        let fetched_data = if let Some(req) = fetch_request {
-            let result = ciyi_game::fetch_words_rank_list(&req.word_to_fetch).await;
-            Some(FetchedData {
-                request: req,
-                result,
+        // 目标词语没有可用的排名列表时换一个词语重新获取
+        let mut fetched_data = None;
+        if fetch_lock.is_some() {
+            for _ in 0..MAX_FETCH_ATTEMPTS {
+                let fetch_request = {
+                    let channel_id = channel_id.clone();
+                    game.call(move |manager| prepare(manager, &channel_id))
+                        .await?
+                };
+                let Some(req) = fetch_request else {
+                    break;
+                };
+
+                let result = provider.fetch(&req.word_to_fetch).await;
+                let rejected = result
+                    .as_ref()
+                    .err()
+                    .is_some_and(|e| p_rank::is_unusable_target(e.as_ref()));
+                if rejected {
+                    let word = req.word_to_fetch.clone();
+                    game.update(move |manager| manager.reject_word(&word))
+                        .await?;
+                }
+                fetched_data = Some(FetchedData {
+                    request: req,
+                    result,
+                });
+                if !rejected {
+                    break;
+                }
+            }
+        }
+
+        game.update(move |manager| commit(manager, &channel_id, fetched_data))
+            .await
+    }
+
+    /// 为频道预取下一轮，已有预取时什么也不做。
+    pub async fn prefetch_round(
+        channel_id: &str,
+        game: &GameHandle,
+        provider: &dyn RankListProvider,
+    ) -> Result<(), GameUnavailable> {
+        let fetch_lock = {
+            let channel_id = channel_id.to_string();
+            game.call(move |manager| {
+                manager
+                    .prepare_prefetch(&channel_id)
+                    .is_some()
+                    .then(|| manager.fetch_lock(&channel_id))
             })
+            .await?
+        };
+        let Some(fetch_lock) = fetch_lock else {
+            return Ok(());
+        };
+        let _fetch_guard = fetch_lock.lock().await;
+
+        let fetch_request = {
+            let channel_id = channel_id.to_string();
+            game.call(move |manager| manager.prepare_prefetch(&channel_id))
+                .await?
+        };
+        let Some(req) = fetch_request else {
+            return Ok(());
+        };
+
+        let result = provider.fetch(&req.word_to_fetch).await;
+        let channel_id = channel_id.to_string();
+        game.update(move |manager| {
+            manager.commit_prefetch(
+                &channel_id,
+                FetchedData {
+                    request: req,
+                    result,
+                },
+            )
+        })
+        .await
+    }
+
+    /// 猜对后立即预取下一轮，不等到换日时才获取。
+    pub async fn prefetch_after_win(
+        channel_id: &str,
+        game: &GameHandle,
+        provider: &dyn RankListProvider,
+    ) {
+        if !p_config::config().prefetch.enabled {
+            return;
+        }
+        let is_finished = {
+            let channel_id = channel_id.to_string();
+            game.call(move |manager| manager.is_finished(&channel_id))
+                .await
+                .unwrap_or(false)
+        };
+        if is_finished && let Err(e) = prefetch_round(channel_id, game, provider).await {
+            log::warn!("Failed to prefetch ciyi round for {channel_id}: {e}");
+        }
+    }
+
+    pub async fn prefetch_all(game: &GameHandle, provider: &dyn RankListProvider) {
+        let Ok(channel_ids) = game.call(|manager| manager.channel_ids()).await else {
+            return;
+        };
+        for channel_id in channel_ids {
+            if let Err(e) = prefetch_round(&channel_id, game, provider).await {
+                log::warn!("Failed to prefetch ciyi round for {channel_id}: {e}");
+            }
+        }
+    }
+
+    /// 群主、群管理员与配置中的插件管理员。
+    pub fn is_admin(event: &MsgEvent) -> bool {
+        let user_id = event.user_id.to_string();
+        p_config::config().plugin.admins.contains(&user_id)
+            || matches!(event.sender.role.as_deref(), Some("owner" | "admin"))
+    }
+
+    /// 发送者的昵称，没有昵称时用 QQ 号。
+    pub fn sender_name(event: &MsgEvent) -> String {
+        event
+            .sender
+            .nickname
+            .clone()
+            .unwrap_or_else(|| event.sender.user_id.to_string())
+    }
+
+    /// 排行榜指令的参数「胜场」或「积分」，未指定时按配置排序。
+    pub fn leaderboard_args(params: &[&str]) -> Result<LeaderboardQuery, String> {
+        let mut sort = p_config::config().score.sort_by;
+        let mut page = 1;
+        let mut window = if p_config::config().season.enabled {
+            TimeWindow::CurrentSeason
         } else {
-            None
+            TimeWindow::All
         };
+        let mut params = params.iter().peekable();
+        while let Some(&param) = params.next() {
+            match param {
+                "胜场" => sort = LeaderboardSort::Wins,
+                "积分" => sort = LeaderboardSort::Points,
+                "全部" | "总" => window = TimeWindow::All,
+                "今日" | "今天" | "日" => window = TimeWindow::Today,
+                "本周" | "周" => window = TimeWindow::Week,
+                "本月" | "月" => window = TimeWindow::Month,
+                "赛季" => {
+                    let period = p_config::config().season.period;
+                    window = match params.peek().and_then(|name| Season::parse(period, name)) {
+                        Some(season) => {
+                            params.next();
+                            TimeWindow::Season(season)
+                        }
+                        None => TimeWindow::CurrentSeason,
+                    };
+                }
+                _ => match param.parse::<usize>() {
+                    Ok(number) if number > 0 => page = number,
+                    _ => window = parse_date_range(param)?,
+                },
+            }
+        }
+        Ok(LeaderboardQuery { sort, window, page })
+    }
 
-        {
-            let mut manager = game_manager_mutex.lock().unwrap();
-            manager.commit_guess(&group_id, &user_id, &username, guess_word, fetched_data)
+    /// 形如 2026-01-01~2026-01-31 的日期范围，含两端。
+    fn parse_date_range(param: &str) -> Result<TimeWindow, String> {
+        let unknown = || {
+            format!(
+                "无法识别的参数：{param}\n可用：胜场、积分、今日、本周、本月、赛季 [名称]、起始日期~结束日期、页码"
+            )
+        };
+        let (start, end) = param.split_once(['~', '～', '至']).ok_or_else(unknown)?;
+        let parse = |date: &str| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d");
+        let (Ok(start), Ok(end)) = (parse(start), parse(end)) else {
+            return Err(unknown());
+        };
+        if start > end {
+            return Err("起始日期不能晚于结束日期".to_string());
         }
+        Ok(TimeWindow::Range(start, end))
     }
 
     pub fn should_process_group(
@@ -742,15 +6587,185 @@ mod p_fn {
 
         event.reply(message);
     }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        use super::*;
+        use crate::p_rank::{RankListFuture, RankListNotFound, ValidatingRankListProvider};
+
+        /// 记录获取次数的来源，前 `failures` 次获取没有排名列表。
+        /// 每次获取都会等待片刻，让并发的请求有机会交错。
+        struct CountingProvider {
+            fetches: AtomicUsize,
+            failures: usize,
+        }
+
+        impl CountingProvider {
+            fn new(failures: usize) -> Self {
+                Self {
+                    fetches: AtomicUsize::new(0),
+                    failures,
+                }
+            }
+        }
+
+        impl RankListProvider for CountingProvider {
+            fn fetch<'a>(&'a self, word: &'a str) -> RankListFuture<'a> {
+                Box::pin(async move {
+                    let attempt = self.fetches.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    if attempt < self.failures {
+                        return Err(RankListNotFound(word.to_string()).into());
+                    }
+                    Ok(vec![word.to_string(), "企业".into(), "东西".into()])
+                })
+            }
+        }
+
+        /// 返回网页而不是排名列表的来源，例如认证页面或代理的错误页。
+        struct GarbageProvider;
+
+        impl RankListProvider for GarbageProvider {
+            fn fetch<'a>(&'a self, _word: &'a str) -> RankListFuture<'a> {
+                Box::pin(async move {
+                    Ok(p_rank::parse_rank_list(
+                        "<html>\n<body>请先登录</body>\n</html>",
+                    ))
+                })
+            }
+        }
+
+        /// 两个猜测用的词语不会被选为目标词语。
+        fn spawn_game() -> GameHandle {
+            let mut manager = CiYiGameManager::default();
+            manager
+                .rejected_words
+                .extend(["企业".into(), "东西".into()]);
+            GameHandle::spawn(manager)
+        }
+
+        fn runtime() -> tokio::runtime::Runtime {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+                .unwrap()
+        }
+
+        async fn guess(
+            game: &GameHandle,
+            provider: &dyn RankListProvider,
+            user: &str,
+            word: &str,
+        ) -> String {
+            try_guess_word(
+                game,
+                provider,
+                "1".into(),
+                user.into(),
+                user.into(),
+                word.into(),
+            )
+            .await
+            .unwrap()
+        }
+
+        #[test]
+        fn concurrent_guesses_share_one_fetch() {
+            p_config::init_default();
+            runtime().block_on(async {
+                let game = spawn_game();
+                let provider = CountingProvider::new(0);
+                let (a, b) = tokio::join!(
+                    guess(&game, &provider, "a", "企业"),
+                    guess(&game, &provider, "b", "东西"),
+                );
+                assert!(a.contains("#2"), "{a}");
+                assert!(b.contains("#3"), "{b}");
+                assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+                let guesses = game
+                    .call(|manager| manager.states["1"].guesses.len())
+                    .await
+                    .unwrap();
+                assert_eq!(guesses, 2);
+            });
+        }
+
+        #[test]
+        fn unusable_targets_are_replaced_up_to_the_attempt_limit() {
+            p_config::init_default();
+            runtime().block_on(async {
+                let game = spawn_game();
+                let provider = CountingProvider::new(MAX_FETCH_ATTEMPTS - 1);
+                let reply = guess(&game, &provider, "a", "企业").await;
+                assert!(reply.contains("#2"), "{reply}");
+                assert_eq!(provider.fetches.load(Ordering::SeqCst), MAX_FETCH_ATTEMPTS);
+                let (rejected, target_rejected) = game
+                    .call(|manager| {
+                        let target = &manager.states["1"].target_word;
+                        (
+                            manager.rejected_words.len(),
+                            manager.rejected_words.contains(target),
+                        )
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(rejected, 2 + MAX_FETCH_ATTEMPTS - 1);
+                assert!(!target_rejected);
+
+                // 每个目标词语都不可用时放弃，不再无限换词
+                let game = spawn_game();
+                let provider = CountingProvider::new(usize::MAX);
+                let reply = guess(&game, &provider, "a", "企业").await;
+                assert_eq!(reply, "词语排名暂时获取不到，请稍后再试");
+                assert_eq!(provider.fetches.load(Ordering::SeqCst), MAX_FETCH_ATTEMPTS);
+            });
+        }
+
+        #[test]
+        fn garbage_rank_lists_do_not_reject_the_target() {
+            p_config::init_default();
+            runtime().block_on(async {
+                let game = spawn_game();
+                let provider = ValidatingRankListProvider::new(Arc::new(GarbageProvider));
+                let reply = guess(&game, &provider, "a", "企业").await;
+                assert_eq!(reply, "词语排名暂时获取不到，请稍后再试");
+                let rejected = game
+                    .call(|manager| manager.rejected_words.len())
+                    .await
+                    .unwrap();
+                assert_eq!(rejected, 2);
+            });
+        }
+
+        #[test]
+        fn small_numbers_after_season_are_pages() {
+            p_config::init_default();
+            let query = leaderboard_args(&["赛季", "2"]).unwrap();
+            assert_eq!(query.window, TimeWindow::CurrentSeason);
+            assert_eq!(query.page, 2);
+
+            let query = leaderboard_args(&["积分", "赛季", "2026Q1", "3"]).unwrap();
+            assert_eq!(query.sort, LeaderboardSort::Points);
+            assert!(matches!(query.window, TimeWindow::Season(s) if s.name() == "2026Q1"));
+            assert_eq!(query.page, 3);
+
+            assert!(leaderboard_args(&["0"]).is_err());
+            assert!(leaderboard_args(&["2026-03-01~2026-02-01"]).is_err());
+        }
+    }
 }
 
 // =============================
 //      Main Plugin Logic
 // =============================
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::time::Duration;
 
-use kovi::PluginBuilder;
+use kovi::{Message, PluginBuilder, log};
 
 use crate::{p_command::COMMAND, p_config::CONFIG};
 
@@ -758,9 +6773,6 @@ use crate::{p_command::COMMAND, p_config::CONFIG};
 async fn main() {
     let bot = PluginBuilder::get_runtime_bot();
     let data_dir = bot.get_data_path();
-    let game_manager = Arc::new(Mutex::new(
-        ciyi_game::CiYiGameManager::new(data_dir.clone()).unwrap(),
-    ));
 
     COMMAND
         .set(p_command::CommandConfig::new(data_dir.clone()).unwrap())
@@ -769,11 +6781,97 @@ async fn main() {
         .set(p_config::Config::new(data_dir.clone()).unwrap())
         .unwrap();
 
+    let game = p_actor::GameHandle::spawn(
+        ciyi_game::CiYiGameManager::new(data_dir.clone(), p_config::config().storage.backend)
+            .unwrap(),
+    );
+
+    let provider = {
+        let config = p_config::config();
+        p_rank::build_provider(&config.rank_list, &config.rank_cache, &data_dir).unwrap()
+    };
+
+    let save_interval_secs = p_config::config().storage.save_interval_secs;
+    if save_interval_secs > 0 {
+        let game = game.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(save_interval_secs));
+            loop {
+                interval.tick().await;
+                if game.call(|manager| manager.save_if_dirty()).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let prefetch_config = &p_config::config().prefetch;
+    if prefetch_config.enabled && prefetch_config.interval_minutes > 0 {
+        let game = game.clone();
+        let provider = Arc::clone(&provider);
+        let period = Duration::from_secs(prefetch_config.interval_minutes * 60);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                p_fn::prefetch_all(&game, provider.as_ref()).await;
+            }
+        });
+    }
+
+    if p_config::config().schedule.unsolved == p_config::UnsolvedPolicy::Reveal {
+        let game = game.clone();
+        let provider = Arc::clone(&provider);
+        let bot = Arc::clone(&bot);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                let Ok(expired) = game.call(|manager| manager.expire_unsolved()).await else {
+                    break;
+                };
+                let channel_config = &p_config::config().channel;
+                for (channel_id, message) in expired {
+                    if let Some(message) = message
+                        && p_fn::should_process_group(
+                            &channel_id,
+                            &channel_config.white,
+                            &channel_config.black,
+                        )
+                        && let Ok(group_id) = channel_id.parse::<i64>()
+                    {
+                        bot.send_group_msg(group_id, Message::new().add_text(&message));
+                    }
+                    p_fn::prefetch_after_win(&channel_id, &game, provider.as_ref()).await;
+                }
+            }
+        });
+    }
+
+    if p_config::config().season.enabled {
+        let game = game.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                if game
+                    .call(|manager| manager.archive_seasons())
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+
     PluginBuilder::on_msg({
-        let game_manager = Arc::clone(&game_manager);
+        let game = game.clone();
+        let provider = Arc::clone(&provider);
 
         move |event| {
-            let game_manager = Arc::clone(&game_manager);
+            let game = game.clone();
+            let provider = Arc::clone(&provider);
 
             async move {
                 let command_map = p_command::commands();
@@ -812,65 +6910,160 @@ async fn main() {
                 // 直接猜测模式
                 if text.chars().count() == 2 {
                     let should_direct_guess = {
-                        let mut manager = game_manager.lock().unwrap();
-                        manager.get_direct_guess_status(&group_id)
+                        let group_id = group_id.clone();
+                        game.call(move |manager| manager.get_direct_guess_status(&group_id))
+                            .await
+                            .unwrap_or(false)
                     };
                     if should_direct_guess {
-                        let response = p_fn::guess_word(&event, &[text], &game_manager).await;
+                        let response =
+                            p_fn::guess_word(&event, &[text], &game, provider.as_ref()).await;
                         p_fn::build_and_send_message(&event, &response);
+                        p_fn::prefetch_after_win(&group_id, &game, provider.as_ref()).await;
                         return;
                     }
                 }
 
                 // 指令解析
                 if let Some((cmd, params)) = p_fn::parse_command(text, &config.plugin.prefixes)
-                    && let Some(function) = command_map.get_function_by_command(cmd) {
-                        match function.as_str() {
-                            "查看插件指令列表" => {
-                                p_fn::build_and_send_message(&event, &p_fn::show_commands());
-                            }
-                            "查看词意游戏规则" => {
-                                p_fn::build_and_send_message(&event, &p_fn::show_rules());
-                            }
-                            "猜测两字词语" => {
-                                let response =
-                                    p_fn::guess_word(&event, &params, &game_manager).await;
-                                p_fn::build_and_send_message(&event, &response);
-                            }
-                            "查看当前频道的词意排行榜" => {
-                                let leaderboard = {
-                                    let manager = game_manager.lock().unwrap();
-                                    manager.get_channel_leaderboard(&group_id)
-                                };
-                                p_fn::build_and_send_message(&event, &leaderboard);
-                            }
-                            "查看所有人的词意排行榜" => {
-                                let leaderboard = {
-                                    let manager = game_manager.lock().unwrap();
-                                    manager.get_global_leaderboard()
-                                };
-                                p_fn::build_and_send_message(&event, &leaderboard);
-                            }
-                            "切换猜测模式" => {
-                                let response = {
-                                    let mut manager = game_manager.lock().unwrap();
-                                    manager.toggle_direct_guess_mode(&group_id)
-                                };
-                                p_fn::build_and_send_message(&event, &response);
-                            }
-                            _ => {}
+                    && let Some(function) = command_map.get_function_by_command(cmd)
+                {
+                    match function.as_str() {
+                        "查看插件指令列表" => {
+                            p_fn::build_and_send_message(&event, &p_fn::show_commands());
                         }
+                        "查看词意游戏规则" => {
+                            p_fn::build_and_send_message(&event, &p_fn::show_rules());
+                        }
+                        "猜测两字词语" => {
+                            let response =
+                                p_fn::guess_word(&event, &params, &game, provider.as_ref()).await;
+                            p_fn::build_and_send_message(&event, &response);
+                            p_fn::prefetch_after_win(&group_id, &game, provider.as_ref()).await;
+                        }
+                        "查看当前频道的词意排行榜" => {
+                            let user_id = event.user_id.to_string();
+                            let leaderboard = match p_fn::leaderboard_args(&params) {
+                                Ok(query) => game
+                                    .call(move |manager| {
+                                        manager.get_channel_leaderboard(&group_id, &query, &user_id)
+                                    })
+                                    .await
+                                    .unwrap_or_else(|e| e.to_string()),
+                                Err(e) => e,
+                            };
+                            p_fn::build_and_send_message(&event, &leaderboard);
+                        }
+                        "查看所有人的词意排行榜" => {
+                            let user_id = event.user_id.to_string();
+                            let leaderboard = match p_fn::leaderboard_args(&params) {
+                                Ok(query) => game
+                                    .call(move |manager| {
+                                        manager.get_global_leaderboard(&query, &user_id)
+                                    })
+                                    .await
+                                    .unwrap_or_else(|e| e.to_string()),
+                                Err(e) => e,
+                            };
+                            p_fn::build_and_send_message(&event, &leaderboard);
+                        }
+                        "查看当前频道的助攻榜" => {
+                            let user_id = event.user_id.to_string();
+                            let leaderboard = match p_fn::leaderboard_args(&params) {
+                                Ok(query) => game
+                                    .call(move |manager| {
+                                        manager.get_assist_leaderboard(&group_id, &query, &user_id)
+                                    })
+                                    .await
+                                    .unwrap_or_else(|e| e.to_string()),
+                                Err(e) => e,
+                            };
+                            p_fn::build_and_send_message(&event, &leaderboard);
+                        }
+                        "查看我的数据" => {
+                            let user_id = event.user_id.to_string();
+                            let username = p_fn::sender_name(&event);
+                            let response = game
+                                .call(move |manager| manager.get_user_stats(&user_id, &username))
+                                .await
+                                .unwrap_or_else(|e| e.to_string());
+                            p_fn::build_and_send_message(&event, &response);
+                        }
+                        "查看往届赛季" => {
+                            // 「词意赛季 [全服] [名称]」
+                            let global = params.contains(&"全服");
+                            let name = params.iter().find(|&&p| p != "全服").map(|p| p.to_string());
+                            let archive = game
+                                .call(move |manager| {
+                                    let channel_id = (!global).then_some(group_id.as_str());
+                                    manager.get_season_archive(channel_id, name.as_deref())
+                                })
+                                .await
+                                .unwrap_or_else(|e| e.to_string());
+                            p_fn::build_and_send_message(&event, &archive);
+                        }
+                        "切换猜测模式" => {
+                            let response = game
+                                .update(move |manager| manager.toggle_direct_guess_mode(&group_id))
+                                .await
+                                .unwrap_or_else(|e| e.to_string());
+                            p_fn::build_and_send_message(&event, &response);
+                        }
+                        "开始新的一局" => {
+                            let response = p_fn::new_round(&event, &game, provider.as_ref()).await;
+                            p_fn::build_and_send_message(&event, &response);
+                        }
+                        "查看本局进度" => {
+                            let response = game
+                                .call(move |manager| manager.get_progress(&group_id))
+                                .await
+                                .unwrap_or_else(|e| e.to_string());
+                            p_fn::build_and_send_message(&event, &response);
+                        }
+                        "回放本局" => {
+                            let response = game
+                                .call(move |manager| manager.get_replay(&group_id))
+                                .await
+                                .unwrap_or_else(|e| e.to_string());
+                            p_fn::build_and_send_message(&event, &response);
+                        }
+                        "获取提示" => {
+                            let response = game
+                                .update(move |manager| manager.take_hint(&group_id))
+                                .await
+                                .unwrap_or_else(|e| e.to_string());
+                            p_fn::build_and_send_message(&event, &response);
+                        }
+                        "放弃本轮" => {
+                            let user_id = event.user_id.to_string();
+                            let is_admin = p_fn::is_admin(&event);
+                            let response = {
+                                let group_id = group_id.clone();
+                                game.update(move |manager| {
+                                    manager.give_up(&group_id, &user_id, is_admin)
+                                })
+                                .await
+                                .unwrap_or_else(|e| e.to_string())
+                            };
+                            p_fn::build_and_send_message(&event, &response);
+                            p_fn::prefetch_after_win(&group_id, &game, provider.as_ref()).await;
+                        }
+                        _ => {}
                     }
+                }
             }
         }
     });
 
-    PluginBuilder::drop({
-        let game_manager = Arc::clone(&game_manager);
-        move || {
-            let game_manager_clone = Arc::clone(&game_manager);
-            async move {
-                game_manager_clone.lock().unwrap().save();
+    PluginBuilder::drop(move || {
+        let game = game.clone();
+        async move {
+            // 在游戏任务外等待写入完成，不占用其工作线程
+            match game.call(|manager| manager.flush()).await {
+                Ok(done) => {
+                    let _ = done.await;
+                }
+                Err(e) => log::error!("Failed to save ciyi game data: {e}"),
             }
         }
     });